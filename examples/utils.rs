@@ -54,6 +54,7 @@ pub fn example_candles() -> Vec<Candle> {
 
 /// Pretty print Metrics
 #[cfg(feature = "metrics")]
+#[allow(dead_code)]
 pub fn print_metrics(metrics: &Metrics, initial_balance: f64) {
     println!("=== Backtest Metrics ===");
     println!("Initial Balance: {:.2}", initial_balance);
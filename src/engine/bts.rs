@@ -170,7 +170,8 @@ fn scenario_place_and_delete_order_with_market_fees() {
     let _expected_total_cost = price + expected_fee; // 110 + 0.11 = 110.11
 
     let order = Order::from((OrderType::Market(price), 1.0, OrderSide::Buy));
-    bt.place_order(order.clone()).unwrap();
+    let id = bt.place_order(order).unwrap();
+    let order = bt.orders().find(|o| o.id() == id).cloned().unwrap();
 
     assert!(!bt.orders.is_empty());
     assert_eq!(bt.balance(), 1000.0);
@@ -215,9 +216,9 @@ fn scenario_place_and_delete_order_with_market_fees() {
         bt.execute_positions(&candle).unwrap(); // close = 120, take profit
 
         assert!(bt.positions.is_empty());
-        assert_eq!(bt.balance(), 1000.0); // balance = 1020 - (10 * 2) (fees)
-        assert_eq!(bt.total_balance(), 1000.0);
-        assert_eq!(bt.free_balance().unwrap(), 1000.0);
+        assert_eq!(bt.balance(), 1008.8); // balance = 1020 - 10 (taker entry) - 1.2 (maker exit at 120)
+        assert_eq!(bt.total_balance(), 1008.8);
+        assert_eq!(bt.free_balance().unwrap(), 1008.8);
     }
 }
 
@@ -252,9 +253,9 @@ fn scenario_open_position_with_market_fees() {
     bt.execute_positions(&candle).unwrap(); // close = 120, take profit
 
     assert!(bt.positions.is_empty());
-    assert_eq!(bt.balance(), 1000.0); // balance = 1020 - (10 * 2) (fees)
-    assert_eq!(bt.total_balance(), 1000.0);
-    assert_eq!(bt.free_balance().unwrap(), 1000.0);
+    assert_eq!(bt.balance(), 1008.8); // balance = 1020 - 10 (taker entry) - 1.2 (maker exit at 120)
+    assert_eq!(bt.total_balance(), 1008.8);
+    assert_eq!(bt.free_balance().unwrap(), 1008.8);
 }
 
 #[test]
@@ -291,7 +292,8 @@ fn scenario_place_and_delete_order() {
     let price = candle.close(); // 110
 
     let order = Order::from((OrderType::Market(price), 1.0, OrderSide::Buy));
-    bt.place_order(order.clone()).unwrap(); // lock amount 110
+    let id = bt.place_order(order).unwrap(); // lock amount 110
+    let order = bt.orders().find(|o| o.id() == id).cloned().unwrap();
 
     assert!(!bt.orders.is_empty());
     assert_eq!(bt.balance(), 1000.0);
@@ -425,9 +427,10 @@ fn scenario_open_short_position_and_take_profit() {
 
     bt.execute_orders(&candle).unwrap();
 
+    // the proceeds of the sale are credited and held as collateral
     assert!(bt.orders.is_empty());
     assert!(!bt.positions.is_empty());
-    assert_eq!(bt.balance(), 860.0);
+    assert_eq!(bt.balance(), 1140.0);
     assert_eq!(bt.total_balance(), 860.0);
     assert_eq!(bt.free_balance().unwrap(), 860.0);
 
@@ -439,8 +442,8 @@ fn scenario_open_short_position_and_take_profit() {
     bt.execute_positions(&candle).unwrap(); // close = 130, p&l = +10
 
     assert!(!bt.positions.is_empty());
-    assert_eq!(bt.balance(), 860.0);
-    assert_eq!(bt.total_balance(), 870.0); // balance + p&l
+    assert_eq!(bt.balance(), 1140.0);
+    assert_eq!(bt.total_balance(), 870.0); // balance - collateral + p&l
     assert_eq!(bt.free_balance().unwrap(), 860.0);
 
     // next tick
@@ -476,7 +479,7 @@ fn scenario_open_short_position_and_stop_loss() {
 
     assert!(bt.orders.is_empty());
     assert!(!bt.positions.is_empty());
-    assert_eq!(bt.balance(), 900.0);
+    assert_eq!(bt.balance(), 1100.0);
     assert_eq!(bt.total_balance(), 900.0);
     assert_eq!(bt.free_balance().unwrap(), 900.0);
 
@@ -488,8 +491,8 @@ fn scenario_open_short_position_and_stop_loss() {
     bt.execute_positions(&candle).unwrap(); // close = 110, p&l = -10
 
     assert!(!bt.positions.is_empty());
-    assert_eq!(bt.balance(), 900.0);
-    assert_eq!(bt.total_balance(), 890.0); // balance + p&l
+    assert_eq!(bt.balance(), 1100.0);
+    assert_eq!(bt.total_balance(), 890.0); // balance - collateral + p&l
     assert_eq!(bt.free_balance().unwrap(), 900.0);
 
     // next tick
@@ -588,6 +591,2085 @@ fn scenario_open_long_position_with_trailing_stop_loss() {
     assert_eq!(bt.free_balance().unwrap(), 990.0);
 }
 
+#[test]
+fn scenario_buy_stop_order_breakout() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, Some((0.1, 0.01))).unwrap();
+
+    let order = Order::from((OrderType::Stop(115.0), 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+
+    assert_eq!(bt.balance(), 1000.0);
+    assert_eq!(bt.free_balance().unwrap(), 885.0);
+
+    // high = 110, not triggered
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    assert!(!bt.orders.is_empty());
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.free_balance().unwrap(), 885.0);
+
+    // high = 119, triggered at 115 and charged the market fee
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    assert!(bt.orders.is_empty());
+    assert!(!bt.positions.is_empty());
+    assert_eq!(bt.balance(), 873.5);
+    assert_eq!(bt.fees_paid(), 11.5);
+    assert_eq!(bt.free_balance().unwrap(), 873.5);
+}
+
+#[test]
+fn scenario_sell_stop_order_breakout() {
+    let data = get_short_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let order = Order::from((OrderType::Stop(125.0), 1.0, OrderSide::Sell));
+    bt.place_order(order).unwrap();
+
+    // low = 131, not triggered
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert!(!bt.orders.is_empty());
+    assert!(bt.positions.is_empty());
+
+    // low = 121, triggered at 125
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.positions().next().unwrap().entry_price().unwrap(), 125.0);
+    assert_eq!(bt.balance(), 1125.0);
+    assert_eq!(bt.free_balance().unwrap(), 875.0);
+}
+
+#[test]
+fn scenario_buy_stop_limit_order() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let order = Order::from((OrderType::StopLimit(115.0, 125.0), 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    assert_eq!(bt.free_balance().unwrap(), 875.0);
+
+    // high = 110, still armed
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.orders().next().unwrap().state(), &OrderState::Armed);
+    assert!(bt.positions.is_empty());
+
+    // high = 119, triggered at 115 which is better than the limit price
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.positions().next().unwrap().entry_price().unwrap(), 115.0);
+    assert_eq!(bt.balance(), 885.0);
+    assert_eq!(bt.free_balance().unwrap(), 885.0);
+
+    let data = get_long_data();
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+    let order = Order::from((OrderType::StopLimit(125.0, 95.0), 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+
+    // high = 129, triggered but the low of 100 does not come back to the limit price
+    while let Some(candle) = bt.next() {
+        bt.execute_orders(&candle).unwrap();
+    }
+    assert_eq!(bt.orders().next().unwrap().state(), &OrderState::Triggered);
+    assert!(bt.positions.is_empty());
+}
+
+#[test]
+fn scenario_sell_stop_limit_order() {
+    let data = get_short_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let order = Order::from((OrderType::StopLimit(135.0, 155.0), 1.0, OrderSide::Sell));
+    bt.place_order(order).unwrap();
+
+    // low = 131, triggered and filled at 155 in the same candle
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert!(bt.orders.is_empty());
+    assert!(!bt.positions.is_empty());
+
+    let data = get_short_data();
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+    let order = Order::from((OrderType::StopLimit(135.0, 165.0), 1.0, OrderSide::Sell));
+    let id = bt.place_order(order).unwrap();
+    let order = bt.orders().find(|o| o.id() == id).cloned().unwrap();
+
+    // triggered, but the market never comes back to 165
+    while let Some(candle) = bt.next() {
+        bt.execute_orders(&candle).unwrap();
+    }
+    assert_eq!(bt.orders().next().unwrap().state(), &OrderState::Triggered);
+    assert!(bt.positions.is_empty());
+
+    bt.delete_order(&order, true).unwrap();
+    assert_eq!(bt.free_balance().unwrap(), 1000.0);
+}
+
+#[test]
+fn scenario_oco_breakout_orders() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let buy_stop = Order::from((OrderType::Stop(115.0), 1.0, OrderSide::Buy));
+    let sell_stop = Order::from((OrderType::Stop(75.0), 1.0, OrderSide::Sell));
+    bt.place_order_group(OrderGroup::Oco(vec![buy_stop, sell_stop]))
+        .unwrap();
+
+    assert_eq!(bt.orders.len(), 2);
+    assert_eq!(bt.free_balance().unwrap(), 810.0);
+
+    // nothing triggered
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.orders.len(), 2);
+
+    // buy stop filled, sell stop cancelled
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.positions.len(), 1);
+    assert_eq!(bt.balance(), 885.0);
+    assert_eq!(bt.free_balance().unwrap(), 885.0);
+}
+
+#[test]
+fn scenario_oco_insufficient_funds() {
+    let data = get_long_data();
+    let mut bt = Backtest::new(data, 100.0, None).unwrap();
+
+    let buy_stop = Order::from((OrderType::Stop(80.0), 1.0, OrderSide::Buy));
+    let sell_stop = Order::from((OrderType::Stop(75.0), 1.0, OrderSide::Sell));
+    let result = bt.place_order_group(OrderGroup::Oco(vec![buy_stop, sell_stop]));
+
+    assert!(matches!(result, Err(Error::InsufficientFunds(_, _))));
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.free_balance().unwrap(), 100.0);
+}
+
+#[test]
+fn scenario_bracket_order_take_profit() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let order = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy));
+    bt.place_order_group(OrderGroup::Bracket(order, 125.0, 85.0)).unwrap();
+
+    // entry filled, legs are placed in the order book
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.positions.len(), 1);
+    assert_eq!(bt.orders.len(), 2);
+    assert_eq!(bt.balance(), 900.0);
+    assert_eq!(bt.free_balance().unwrap(), 900.0);
+
+    // low = 90, high = 119, nothing
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.positions.len(), 1);
+    assert_eq!(bt.orders.len(), 2);
+
+    // high = 129, take-profit leg filled, stop-loss leg cancelled
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.balance(), 1025.0);
+    assert_eq!(bt.free_balance().unwrap(), 1025.0);
+}
+
+#[test]
+fn scenario_bracket_order_stop_loss() {
+    let data = get_short_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let order = Order::from((OrderType::Market(140.0), 1.0, OrderSide::Buy));
+    bt.place_order_group(OrderGroup::Bracket(order, 170.0, 125.0)).unwrap();
+
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.orders.len(), 2);
+
+    // low = 121, stop-loss leg filled at 125
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.balance(), 985.0);
+}
+
+fn run_bracket_intrabar_path(intrabar_path: IntrabarPath) -> f64 {
+    let data = get_long_data();
+    let mut bt = Backtest::new(data, 1000.0, None)
+        .unwrap()
+        .with_intrabar_path(intrabar_path);
+
+    let order = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy));
+    bt.place_order_group(OrderGroup::Bracket(order, 115.0, 95.0)).unwrap();
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // high = 119 and low = 90, both legs are reached
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert!(bt.orders.is_empty());
+    bt.balance()
+}
+
+#[test]
+fn scenario_bracket_intrabar_path() {
+    assert_eq!(run_bracket_intrabar_path(IntrabarPath::Optimistic), 1015.0);
+    assert_eq!(run_bracket_intrabar_path(IntrabarPath::Pessimistic), 995.0);
+    // open = 100, the low is nearer than the high
+    assert_eq!(run_bracket_intrabar_path(IntrabarPath::OhlcHeuristic), 995.0);
+}
+
+#[test]
+fn scenario_bracket_legs_cancelled_on_close() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let order = Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy));
+    bt.place_order_group(OrderGroup::Bracket(order, 125.0, 85.0)).unwrap();
+
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.orders.len(), 2);
+
+    let position = bt.positions().next().cloned().unwrap();
+    bt.close_position(&position, 105.0, true).unwrap();
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.balance(), 1005.0);
+    assert_eq!(bt.free_balance().unwrap(), 1005.0);
+}
+
+#[test]
+fn scenario_partial_close_position() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, Some((0.1, 0.01))).unwrap();
+
+    let order = Order::from((OrderType::Market(100.0), 2.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.balance(), 780.0); // 1000 - 200 - 20 (fees)
+
+    // scale out half of the position
+    let position = bt.positions().next().cloned().unwrap();
+    let pnl = bt.close_position_partial(&position, 1.0, 110.0).unwrap();
+    assert_eq!(pnl, 10.0);
+    assert_eq!(bt.positions().next().unwrap().quantity, 1.0);
+    assert_eq!(bt.balance(), 879.0); // 780 + 100 + 10 - 11 (fees on the 110 notional)
+    assert_eq!(bt.fees_paid(), 31.0);
+
+    // can not close more than the remaining quantity
+    let result = bt.close_position_partial(&position, 2.0, 110.0);
+    assert!(matches!(result, Err(Error::InvalidQuantity(2.0))));
+
+    // the rest is closed with the stored quantity
+    let pnl = bt.close_position(&position, 120.0, true).unwrap();
+    assert_eq!(pnl, 20.0);
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 987.0); // 879 + 100 + 20 - 12 (fees on the 120 notional)
+    assert_eq!(bt.free_balance().unwrap(), 987.0);
+}
+
+#[test]
+fn scenario_maker_taker_fees_per_fill() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    // the limit entry pays the maker fee and the stop-loss exit the taker fee
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_fee_model(MakerTaker(0.0, 0.01));
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::TakeProfitAndStopLoss(0.0, 100.0);
+    let order = Order::from((OrderType::Limit(95.0), exit_rule, 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+    // the candle opens at 90 below the limit, filled at the open without fees
+    assert_eq!(bt.balance(), 910.0);
+    assert_eq!(bt.fees_paid(), 0.0);
+
+    // low = 90, stopped at 100
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.fees_paid(), 1.0);
+    assert_eq!(bt.balance(), 1009.0);
+    assert_eq!(bt.traded_volume(), 190.0);
+}
+
+#[test]
+fn scenario_maker_rebate_and_tiered_fees() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let tiers = vec![(0.0, MakerTaker(-0.01, 0.1)), (100.0, MakerTaker(-0.01, 0.01))];
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_fee_model(TieredFees(tiers));
+
+    bt.next().unwrap();
+    let candle = bt.next().unwrap();
+    // first tier: the maker rebate is credited
+    bt.place_order(Order::from((OrderType::Limit(100.0), 1.0, OrderSide::Buy)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.fees_paid(), -1.0);
+    assert_eq!(bt.balance(), 901.0);
+
+    // second tier: 100 has been traded
+    let position = bt.positions().next().cloned().unwrap();
+    bt.close_position(&position, 100.0, true).unwrap();
+    assert_eq!(bt.fees_paid(), 0.0);
+    assert_eq!(bt.balance(), 1000.0);
+}
+
+#[test]
+fn scenario_invalid_market_fees() {
+    let data = get_long_data();
+    for market_fees in [(-0.001, 0.001), (f64::NAN, 0.001), (0.001, f64::INFINITY)] {
+        let result = Backtest::new(data.clone(), 1000.0, Some(market_fees));
+        assert!(matches!(result, Err(Error::NegZeroFees)));
+    }
+    // a zero taker fee and a maker rebate are valid
+    assert!(Backtest::new(data, 1000.0, Some((0.0, -0.001))).is_ok());
+}
+
+#[test]
+fn scenario_equity_commission_and_sell_fees() {
+    let data = get_long_data();
+    let balance = 100_000.0;
+    let fees = (PerShare(0.005, 1.0, f64::INFINITY), SellFees(0.0001, 0.01, 0.5));
+    let mut bt = Backtest::new(data, balance, None).unwrap().with_fee_model(fees);
+
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Market(100.0), 100.0, OrderSide::Buy)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    // 100 × 0.005 = 0.5, raised to the minimum commission
+    assert_eq!(bt.fees_paid(), 1.0);
+
+    // the sell pays the commission, 10000 × 0.0001 and 100 × 0.01 capped to 0.5
+    let position = bt.positions().next().cloned().unwrap();
+    bt.close_position(&position, 100.0, true).unwrap();
+    assert_eq!(bt.fees_paid(), 3.5);
+    assert_eq!(bt.balance(), 99_996.5);
+}
+
+#[test]
+fn scenario_leverage_locks_initial_margin() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_leverage(10.0, 0.05, 0.01)
+        .unwrap();
+
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Market(100.0), 50.0, OrderSide::Buy)))
+        .unwrap();
+    assert_eq!(bt.free_balance().unwrap(), 500.0);
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.balance(), 500.0);
+
+    // the profit is made on the whole notional
+    let position = bt.positions().next().cloned().unwrap();
+    let pnl = bt.close_position(&position, 110.0, true).unwrap();
+    assert_eq!(pnl, 500.0);
+    assert_eq!(bt.balance(), 1500.0);
+    assert_eq!(bt.free_balance().unwrap(), 1500.0);
+}
+
+#[test]
+fn scenario_liquidation() {
+    let data = get_short_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_leverage(10.0, 0.08, 0.01)
+        .unwrap();
+
+    // notional of 7000 with a margin of 700
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Market(140.0), 50.0, OrderSide::Buy)))
+        .unwrap();
+    bt.place_order(Order::from((OrderType::Limit(100.0), 1.0, OrderSide::Buy)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert_eq!(bt.positions.len(), 1);
+
+    // low = 121, the equity of 1000 - 950 is below the maintenance margin of 6050 × 0.08
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert!(bt.orders.is_empty());
+    // the equity 1000 - 50 × (140 - price) reaches 50 × price × 0.08 at the liquidation price
+    let price = 6000.0 / 46.0;
+    let fee = 50.0 * price * 0.01;
+    assert!((bt.fees_paid() - fee).abs() < 1e-9);
+    assert!((bt.balance() - (1000.0 - 50.0 * (140.0 - price) - fee)).abs() < 1e-9);
+
+    #[cfg(feature = "metrics")]
+    {
+        let liquidations = bt.events().filter(|e| matches!(e, Event::Liquidation(_))).count();
+        assert_eq!(liquidations, 1);
+    }
+}
+
+fn get_flat_then_gap_data((open, high, low, close): (f64, f64, f64, f64)) -> Vec<Candle> {
+    [(100.0, 100.0, 100.0, 100.0), (open, high, low, close)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (open, high, low, close))| {
+            let open_time = DateTime::from_timestamp_secs(1515151515 + i as i64).unwrap();
+            CandleBuilder::builder()
+                .open(open)
+                .high(high)
+                .low(low)
+                .close(close)
+                .volume(1.0)
+                .open_time(open_time)
+                .close_time(open_time + chrono::TimeDelta::seconds(1))
+                .build()
+                .unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn scenario_liquidation_wick() {
+    let data = get_flat_then_gap_data((100.0, 100.0, 50.0, 100.0));
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_leverage(10.0, 0.05, 0.01)
+        .unwrap();
+
+    // notional of 5000 with a margin of 500
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Market(100.0), 50.0, OrderSide::Buy)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    bt.execute_positions(&candle).unwrap();
+
+    // the candle wicks to 50 and closes at 100, the position is liquidated inside the candle
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    // the equity 1000 - 50 × (100 - price) reaches 50 × price × 0.05 at the liquidation price
+    let price = 4000.0 / 47.5;
+    let fee = 50.0 * price * 0.01;
+    assert!((bt.balance() - (1000.0 - 50.0 * (100.0 - price) - fee)).abs() < 1e-9);
+    assert_eq!(bt.shortfall(), 0.0);
+}
+
+#[test]
+fn scenario_liquidation_gap() {
+    let data = get_flat_then_gap_data((85.0, 86.0, 84.0, 85.0));
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_leverage(10.0, 0.05, 0.01)
+        .unwrap();
+
+    // notional of 10000 with a margin of 1000
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Market(100.0), 100.0, OrderSide::Buy)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert_eq!(bt.positions.len(), 1);
+
+    // gap to 85, the loss of 1500 is larger than the margin and the free balance
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 0.0);
+    assert_eq!(bt.free_balance().unwrap(), 0.0);
+    assert_eq!(bt.shortfall(), 500.0);
+    assert_eq!(bt.fees_paid(), 0.0);
+
+    #[cfg(feature = "metrics")]
+    {
+        let liquidations = bt.events().filter(|e| matches!(e, Event::Liquidation(_))).count();
+        assert_eq!(liquidations, 1);
+    }
+
+    // unlevered short, the proceeds of 1000 are held with a margin of 1000
+    let data = get_flat_then_gap_data((250.0, 255.0, 245.0, 250.0));
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_leverage(1.0, 0.5, 0.0)
+        .unwrap();
+
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Market(100.0), 10.0, OrderSide::Sell)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert_eq!(bt.positions.len(), 1);
+
+    // gap to 250, the loss of 1500 is larger than the margin
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 0.0);
+    assert_eq!(bt.short_collateral(), 0.0);
+    assert_eq!(bt.free_balance().unwrap(), 0.0);
+    assert_eq!(bt.shortfall(), 500.0);
+}
+
+#[test]
+fn scenario_invalid_leverage() {
+    let data = get_long_data();
+    let result = Backtest::new(data.clone(), 1000.0, None)
+        .unwrap()
+        .with_leverage(0.5, 0.0, 0.0);
+    assert!(matches!(result, Err(Error::InvalidMargin(0.5))));
+    // the maintenance margin must be lower than the initial margin of 10%
+    let result = Backtest::new(data, 1000.0, None).unwrap().with_leverage(10.0, 0.1, 0.0);
+    assert!(matches!(result, Err(Error::InvalidMargin(0.1))));
+}
+
+#[test]
+fn scenario_partial_close_whole_position() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let order = Order::from((OrderType::Market(100.0), 2.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    let position = bt.positions().next().cloned().unwrap();
+    let pnl = bt.close_position_partial(&position, 2.0, 90.0).unwrap();
+    assert_eq!(pnl, -20.0);
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 980.0);
+}
+
+#[test]
+fn scenario_partial_close_rounded_quantities() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let order = Order::from((OrderType::Market(100.0), 0.3, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // 0.3 - 0.1 is not exactly 0.2 in floating point
+    let position = bt.positions().next().cloned().unwrap();
+    bt.close_position_partial(&position, 0.1, 100.0).unwrap();
+    let position = bt.positions().next().cloned().unwrap();
+    bt.close_position_partial(&position, 0.2, 100.0).unwrap();
+    assert!(bt.positions.is_empty());
+    assert!((bt.balance() - balance).abs() < 1e-9);
+
+    let order = Order::from((OrderType::Market(100.0), 0.3, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    let position = bt.positions().next().cloned().unwrap();
+    let result = bt.close_position_partial(&position, 0.31, 100.0);
+    assert!(matches!(result, Err(Error::InvalidQuantity(0.31))));
+}
+
+#[test]
+fn scenario_amend_order() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let order = Order::from((OrderType::Limit(70.0), 1.0, OrderSide::Buy));
+    let id = bt.place_order(order).unwrap();
+    assert_eq!(bt.free_balance().unwrap(), 930.0);
+
+    // move the limit price up, lock the difference
+    bt.amend_order(id, Amendment::EntryType(OrderType::Limit(85.0)))
+        .unwrap();
+    assert_eq!(bt.free_balance().unwrap(), 915.0);
+
+    // reduce the quantity, unlock the difference
+    bt.amend_order(id, Amendment::Quantity(0.5)).unwrap();
+    assert_eq!(bt.free_balance().unwrap(), 957.5);
+
+    let result = bt.amend_order(id, Amendment::Quantity(20.0));
+    assert!(matches!(result, Err(Error::InsufficientFunds(_, _))));
+    assert_eq!(bt.free_balance().unwrap(), 957.5);
+
+    let result = bt.amend_order(id.wrapping_add(1), Amendment::Quantity(1.0));
+    assert!(matches!(result, Err(Error::OrderNotFound)));
+
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    let position = bt.positions().next().unwrap();
+    assert_eq!(position.entry_price().unwrap(), 85.0);
+    assert_eq!(position.quantity, 0.5);
+    assert_eq!(bt.balance(), 957.5);
+    assert_eq!(bt.free_balance().unwrap(), 957.5);
+}
+
+#[test]
+fn scenario_amend_position_stop_loss() {
+    let data = get_short_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let stop_loss = OrderType::TakeProfitAndStopLoss(0.0, 100.0);
+    let order = Order::from((OrderType::Market(140.0), stop_loss, 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // tighten the stop-loss
+    let id = bt.positions().next().unwrap().id();
+    let stop_loss = OrderType::TakeProfitAndStopLoss(0.0, 125.0);
+    bt.amend_position(id, Amendment::ExitRule(Some(stop_loss))).unwrap();
+
+    let result = bt.amend_position(id, Amendment::Quantity(2.0));
+    assert!(matches!(result, Err(Error::MismatchedOrderType)));
+
+    // low = 121, stop-loss matched
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 985.0);
+}
+
+#[test]
+fn scenario_deterministic_ids() {
+    let data = get_long_data();
+    let mut bt = Backtest::new(data, 1000.0, None).unwrap();
+
+    let run = |bt: &mut Backtest| {
+        let mut ids = Vec::new();
+        bt.run(|bt, candle| {
+            let order = Order::from((OrderType::Market(candle.close()), 1.0, OrderSide::Buy));
+            ids.push(bt.place_order(order)?);
+            Ok(())
+        })
+        .unwrap();
+        ids.extend(bt.positions().map(|p| p.id()));
+        ids
+    };
+
+    let first_ids = run(&mut bt);
+    #[cfg(feature = "metrics")]
+    let first_events = bt.events().cloned().collect::<Vec<_>>();
+    bt.reset();
+    let second_ids = run(&mut bt);
+
+    assert_eq!(first_ids, vec![1, 3, 5, 2, 4, 6]);
+    assert_eq!(first_ids, second_ids);
+    #[cfg(feature = "metrics")]
+    assert_eq!(first_events, bt.events().cloned().collect::<Vec<_>>());
+}
+
+#[test]
+fn scenario_time_in_force_expiry() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let mut ioc = Order::from((OrderType::Limit(70.0), 1.0, OrderSide::Buy));
+    ioc.set_time_in_force(TimeInForce::ImmediateOrCancel);
+    let mut gtb = Order::from((OrderType::Limit(75.0), 1.0, OrderSide::Buy));
+    gtb.set_time_in_force(TimeInForce::GoodTillBars(2));
+    let gtc = Order::from((OrderType::Limit(80.0), 1.0, OrderSide::Buy));
+    bt.place_order(ioc).unwrap();
+    bt.place_order(gtb).unwrap();
+    bt.place_order(gtc).unwrap();
+    assert_eq!(bt.free_balance().unwrap(), 775.0);
+
+    // low = 80, only the good-till-cancelled order is filled, the immediate-or-cancel order expires
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.positions.len(), 1);
+    assert_eq!(bt.orders.len(), 1);
+    assert_eq!(bt.free_balance().unwrap(), 845.0);
+
+    // low = 90, the good-till-bars order expires after 2 candles
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.balance(), 920.0);
+    assert_eq!(bt.free_balance().unwrap(), 920.0);
+}
+
+#[test]
+fn scenario_good_till_date_expiry() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    // candles close at 1515151516
+    let mut expired = Order::from((OrderType::Limit(85.0), 1.0, OrderSide::Buy));
+    expired.set_time_in_force(TimeInForce::GoodTillDate(
+        DateTime::from_timestamp_secs(1515151515).unwrap(),
+    ));
+    let mut alive = Order::from((OrderType::Limit(85.0), 1.0, OrderSide::Buy));
+    alive.set_time_in_force(TimeInForce::GoodTillDate(
+        DateTime::from_timestamp_secs(1515151516).unwrap(),
+    ));
+    bt.place_order(expired).unwrap();
+    bt.place_order(alive).unwrap();
+
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.positions.len(), 1);
+    assert_eq!(bt.balance(), 915.0);
+    assert_eq!(bt.free_balance().unwrap(), 915.0);
+}
+
+#[test]
+fn scenario_take_profit_ladder() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let ladder = OrderType::TakeProfitLadder(vec![(105.0, 0.5), (115.0, 0.25), (125.0, 0.25)], 85.0);
+    let order = Order::from((OrderType::Market(100.0), ladder, 2.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.balance(), 800.0);
+
+    // high = 119, the first two levels are reached
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    let position = bt.positions().next().unwrap();
+    assert_eq!(position.quantity, 0.5);
+    assert_eq!(
+        position.exit_rule(),
+        &Some(OrderType::TakeProfitLadder(vec![(125.0, 1.0)], 85.0))
+    );
+    assert_eq!(bt.balance(), 962.5); // 800 + 105 + 57.5
+    assert_eq!(bt.total_balance(), 967.5); // + 0.5 * 10
+
+    // high = 129, the last level closes the position
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 1025.0); // 962.5 + 62.5
+    assert_eq!(bt.free_balance().unwrap(), 1025.0);
+}
+
+#[test]
+fn scenario_take_profit_ladder_stop_loss_remainder() {
+    let data = get_short_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let ladder = OrderType::TakeProfitLadder(vec![(150.0, 0.5)], 125.0);
+    let order = Order::from((OrderType::Market(140.0), ladder, 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // high = 150, half closed at the take-profit, low = 121, the rest is stopped
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 997.5); // 860 + 75 + 62.5
+}
+
+#[test]
+fn scenario_take_profit_ladder_invalid_fraction() {
+    let data = get_long_data();
+    let mut bt = Backtest::new(data, 1000.0, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let ladder = OrderType::TakeProfitLadder(vec![(105.0, 1.5)], 0.0);
+    let order = Order::from((OrderType::Market(100.0), ladder, 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    let result = bt.execute_positions(&candle);
+    assert!(matches!(result, Err(Error::InvalidFraction(1.5))));
+}
+
+#[test]
+fn scenario_break_even_stop() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, Some((0.1, 0.1))).unwrap();
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::BreakEvenStop(85.0, 115.0);
+    let order = Order::from((OrderType::Market(100.0), exit_rule, 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.balance(), 890.0);
+
+    // high = 119, the stop-loss moves to the entry price plus the fees
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    let position = bt.positions().next().unwrap();
+    assert_eq!(position.exit_rule(), &Some(OrderType::BreakEvenStop(120.0, 0.0)));
+
+    // the candle opens at 110 below the stop-loss, filled at the open (fees on the 110 notional)
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 989.0);
+    assert_eq!(bt.free_balance().unwrap(), 989.0);
+}
+
+#[test]
+fn scenario_break_even_stop_loss_before_trigger() {
+    let data = get_short_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::BreakEvenStop(145.0, 120.0);
+    let order = Order::from((OrderType::Market(140.0), exit_rule, 1.0, OrderSide::Sell));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // high = 150, the stop-loss is hit before the trigger
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 995.0);
+}
+
+#[test]
+fn scenario_stepped_stop_long() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::SteppedStop(85.0, 105.0, 5.0);
+    let order = Order::from((OrderType::Market(100.0), exit_rule, 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // high = 119, three steps are reached
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    let position = bt.positions().next().unwrap();
+    assert_eq!(position.exit_rule(), &Some(OrderType::SteppedStop(100.0, 120.0, 5.0)));
+
+    // low = 100
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 1000.0);
+}
+
+#[test]
+fn scenario_stepped_stop_short() {
+    let data = get_short_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::SteppedStop(155.0, 135.0, 5.0);
+    let order = Order::from((OrderType::Market(140.0), exit_rule, 1.0, OrderSide::Sell));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // low = 121, three steps are reached
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    let position = bt.positions().next().unwrap();
+    assert_eq!(position.exit_rule(), &Some(OrderType::SteppedStop(140.0, 120.0, 5.0)));
+
+    // high = 140
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 1000.0);
+}
+
+#[test]
+fn scenario_stepped_stop_tiny_step() {
+    let data = get_flat_then_gap_data((100.0, 1000.0, 99.0, 900.0));
+    let mut bt = Backtest::new(data, 1000.0, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::SteppedStop(90.0, 101.0, 1e-6);
+    let order = Order::from((OrderType::Market(100.0), exit_rule, 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // high = 1000, the stop-loss moves by 899 + 1e-6 at once
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    let position = bt.positions().next().unwrap();
+    let Some(OrderType::SteppedStop(stop_loss, trigger, _)) = position.exit_rule() else {
+        panic!("the exit rule should be a stepped stop");
+    };
+    assert!((stop_loss - 989.0).abs() < 1e-5);
+    assert!(*trigger > 1000.0 && *trigger - 1000.0 < 1e-5);
+}
+
+#[test]
+fn scenario_stepped_stop_invalid_step() {
+    let data = get_long_data();
+    let mut bt = Backtest::new(data, 1000.0, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::SteppedStop(85.0, 105.0, 0.0);
+    let order = Order::from((OrderType::Market(100.0), exit_rule, 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    let result = bt.execute_positions(&candle);
+    assert!(matches!(result, Err(Error::NegZeroTrailingStop)));
+}
+
+#[test]
+fn scenario_trailing_stop_distance_activation() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::TrailingStopDistance(100.0, 12.0, 115.0);
+    let order = Order::from((OrderType::Market(100.0), exit_rule, 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // high = 119, the trailing stop is activated
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    let position = bt.positions().next().unwrap();
+    assert_eq!(
+        position.exit_rule(),
+        &Some(OrderType::TrailingStopDistance(119.0, 12.0, 0.0))
+    );
+
+    // low = 100, the stop at 107 is hit
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 1007.0);
+}
+
+#[test]
+fn scenario_trailing_stop_distance_short() {
+    let data = get_short_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::TrailingStopDistance(140.0, 12.0, 0.0);
+    let order = Order::from((OrderType::Market(140.0), exit_rule, 1.0, OrderSide::Sell));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // low = 121
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    let position = bt.positions().next().unwrap();
+    assert_eq!(
+        position.exit_rule(),
+        &Some(OrderType::TrailingStopDistance(121.0, 12.0, 0.0))
+    );
+
+    // high = 140, the stop at 133 is hit
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 1007.0);
+}
+
+#[test]
+fn scenario_trailing_stop_percent_activation() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::TrailingStopActivation(100.0, 15.0, 120.0);
+    let order = Order::from((OrderType::Market(100.0), exit_rule.clone(), 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // high = 119, the activation price is not reached
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    let position = bt.positions().next().unwrap();
+    assert_eq!(position.exit_rule(), &Some(exit_rule));
+
+    // high = 129, the trailing stop is activated
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    let position = bt.positions().next().unwrap();
+    assert_eq!(
+        position.exit_rule(),
+        &Some(OrderType::TrailingStopActivation(129.0, 15.0, 0.0))
+    );
+}
+
+#[test]
+fn scenario_trailing_stop_distance_invalid() {
+    let data = get_long_data();
+    let mut bt = Backtest::new(data, 1000.0, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::TrailingStopDistance(100.0, 0.0, 0.0);
+    let order = Order::from((OrderType::Market(100.0), exit_rule, 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    let result = bt.execute_positions(&candle);
+    assert!(matches!(result, Err(Error::NegZeroTrailingStop)));
+}
+
+#[test]
+fn scenario_time_stop_bars() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::TakeProfitAndStopLoss(150.0, 85.0);
+    let mut order = Order::from((OrderType::Market(100.0), exit_rule, 1.0, OrderSide::Buy));
+    order.set_time_stop(Some(TimeStop::Bars(2)));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert_eq!(bt.positions.len(), 1);
+
+    // closed at the close of the second candle after the entry
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 1020.0);
+}
+
+#[test]
+fn scenario_time_stop_after_take_profit() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::TakeProfitAndStopLoss(115.0, 0.0);
+    let mut order = Order::from((OrderType::Market(100.0), exit_rule, 1.0, OrderSide::Buy));
+    order.set_time_stop(Some(TimeStop::Bars(1)));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // high = 119, the take-profit is reached before the candle close
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 1015.0);
+}
+
+#[test]
+fn scenario_time_stop_date() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    bt.run(|bt, _| {
+        if bt.index == 0 {
+            let mut order = Order::from((OrderType::Market(90.0), 1.0, OrderSide::Buy));
+            order.set_time_stop(Some(TimeStop::Date(DateTime::from_timestamp_secs(1515151516).unwrap())));
+            bt.place_order(order)?;
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    // closed at the close of the entry candle
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 1010.0);
+}
+
+#[test]
+fn scenario_hedge_mode_opposite_positions() {
+    let data = get_long_data();
+    let mut bt = Backtest::new(data, 1000.0, None).unwrap();
+    assert_eq!(bt.account_mode(), &AccountMode::Hedge);
+
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Limit(90.0), 1.0, OrderSide::Buy)))
+        .unwrap();
+    bt.place_order(Order::from((OrderType::Limit(100.0), 1.0, OrderSide::Sell)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.positions.len(), 2);
+}
+
+#[test]
+fn scenario_one_way_mode_netting() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_account_mode(AccountMode::OneWay);
+
+    // same side orders are merged with an averaged entry price
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Limit(90.0), 1.0, OrderSide::Buy)))
+        .unwrap();
+    bt.place_order(Order::from((OrderType::Limit(80.0), 1.0, OrderSide::Buy)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.positions.len(), 1);
+    let position = bt.positions().next().unwrap().clone();
+    assert_eq!(position.quantity, 2.0);
+    assert_eq!(position.entry_price().unwrap(), 85.0);
+    assert_eq!(bt.balance(), 830.0);
+
+    // an opposite order reduces the position
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Limit(105.0), 1.0, OrderSide::Sell)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    let reduced = bt.positions().next().unwrap();
+    assert_eq!(reduced.id(), position.id());
+    assert_eq!(reduced.quantity, 1.0);
+    assert_eq!(bt.balance(), 935.0); // 830 + 85 + 20
+
+    // a bigger opposite order flips the position
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Limit(110.0), 3.0, OrderSide::Sell)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.positions.len(), 1);
+    let flipped = bt.positions().next().unwrap();
+    assert!(matches!(flipped.side, PositionSide::Short));
+    assert_eq!(flipped.quantity, 2.0);
+    assert_eq!(flipped.entry_price().unwrap(), 110.0);
+    assert_eq!(bt.balance(), 1265.0); // 935 + 85 + 25 + 220 (proceeds of the short)
+    assert_eq!(bt.free_balance().unwrap(), 825.0);
+}
+
+#[test]
+fn scenario_one_way_mode_close_position() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_account_mode(AccountMode::OneWay);
+
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Limit(90.0), 1.0, OrderSide::Buy)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Limit(110.0), 1.0, OrderSide::Sell)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 1020.0);
+    assert_eq!(bt.free_balance().unwrap(), 1020.0);
+}
+
+#[test]
+fn scenario_add_to_position() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap().with_max_pyramids(1);
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::TakeProfitAndStopLoss(125.0, 0.0);
+    let order = Order::from((OrderType::Limit(90.0), exit_rule, 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+    let id = bt.positions().next().unwrap().id();
+
+    let candle = bt.next().unwrap();
+    let order = Order::from((OrderType::Limit(100.0), 1.0, OrderSide::Buy));
+    bt.add_to_position(id, order).unwrap();
+    let order = Order::from((OrderType::Limit(95.0), 1.0, OrderSide::Buy));
+    let result = bt.add_to_position(id, order);
+    assert!(matches!(result, Err(Error::MaxPyramid(1))));
+    let order = Order::from((OrderType::Limit(110.0), 1.0, OrderSide::Sell));
+    let result = bt.add_to_position(id, order);
+    assert!(matches!(result, Err(Error::MismatchedOrderType)));
+
+    bt.execute_orders(&candle).unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert_eq!(bt.positions.len(), 1);
+    let position = bt.positions().next().unwrap();
+    assert_eq!(position.id(), id);
+    assert_eq!(position.quantity, 2.0);
+    assert_eq!(position.entry_price().unwrap(), 95.0);
+    assert_eq!(position.entries(), 2);
+    assert_eq!(position.estimate_pnl(110.0).unwrap(), 30.0);
+    assert_eq!(bt.balance(), 810.0);
+
+    // high = 129, the single exit rule closes the whole position
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 1060.0); // 810 + 190 + 60
+}
+
+#[test]
+fn scenario_add_to_position_cancelled_on_close() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::TakeProfitAndStopLoss(115.0, 0.0);
+    let order = Order::from((OrderType::Limit(90.0), exit_rule, 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+    let id = bt.positions().next().unwrap().id();
+
+    let order = Order::from((OrderType::Limit(50.0), 1.0, OrderSide::Buy));
+    bt.add_to_position(id, order).unwrap();
+    assert_eq!(bt.free_balance().unwrap(), 860.0);
+
+    // high = 119, the take-profit closes the position and the pending addition
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.balance(), 1025.0);
+    assert_eq!(bt.free_balance().unwrap(), 1025.0);
+}
+
+#[test]
+fn scenario_add_to_position_cancelled_on_close_order() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    let id = bt.positions().next().unwrap().id();
+
+    // the addition is matched before the close order fills
+    let order = Order::from((OrderType::Limit(85.0), 1.0, OrderSide::Buy));
+    bt.add_to_position(id, order).unwrap();
+    bt.place_close_order(id, OrderType::Limit(105.0)).unwrap();
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.balance(), 1005.0);
+    assert_eq!(bt.free_balance().unwrap(), 1005.0);
+
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert_eq!(bt.balance(), 1005.0);
+}
+
+#[test]
+fn scenario_reduce_only_order() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Limit(90.0), 2.0, OrderSide::Buy)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    let id = bt.positions().next().unwrap().id();
+    assert_eq!(bt.balance(), 820.0);
+
+    let order = Order::from((OrderType::Limit(115.0), 1.0, OrderSide::Buy));
+    let result = bt.place_reduce_only_order(id, order);
+    assert!(matches!(result, Err(Error::MismatchedOrderType)));
+    let order = Order::from((OrderType::Limit(115.0), 5.0, OrderSide::Sell));
+    let result = bt.place_reduce_only_order(id, order);
+    assert!(matches!(result, Err(Error::InvalidQuantity(5.0))));
+
+    // reduce-only orders do not lock any funds
+    let order = Order::from((OrderType::Limit(115.0), 1.0, OrderSide::Sell));
+    bt.place_reduce_only_order(id, order).unwrap();
+    assert_eq!(bt.free_balance().unwrap(), 820.0);
+
+    // high = 110, the limit price is not reached
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.orders.len(), 1);
+
+    // high = 119
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.positions().next().unwrap().quantity, 1.0);
+    assert_eq!(bt.balance(), 935.0); // 820 + 90 + 25
+}
+
+#[test]
+fn scenario_close_order() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Limit(90.0), 2.0, OrderSide::Buy)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    let id = bt.positions().next().unwrap().id();
+
+    // the market price is outside of the candle range, the order expires
+    let candle = bt.next().unwrap();
+    bt.place_close_order(id, OrderType::Market(200.0)).unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.positions.len(), 1);
+
+    bt.place_close_order(id, OrderType::Market(110.0)).unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 1040.0);
+    assert_eq!(bt.free_balance().unwrap(), 1040.0);
+}
+
+fn get_gap_data() -> Vec<Candle> {
+    let candle1 = CandleBuilder::builder()
+        .open(100.0)
+        .high(110.0)
+        .low(95.0)
+        .close(105.0)
+        .volume(1.0)
+        .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
+        .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
+        .build()
+        .unwrap();
+    let candle2 = CandleBuilder::builder()
+        .open(108.0)
+        .high(112.0)
+        .low(100.0)
+        .close(111.0)
+        .volume(1.0)
+        .open_time(DateTime::from_timestamp_secs(1515151516).unwrap())
+        .close_time(DateTime::from_timestamp_secs(1515151517).unwrap())
+        .build()
+        .unwrap();
+
+    vec![candle1, candle2]
+}
+
+#[test]
+fn scenario_next_open_execution() {
+    let data = get_gap_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_execution_model(ExecutionModel::NextOpen);
+
+    bt.run(|bt, candle| {
+        if bt.index == 0 {
+            bt.place_order(Order::from((OrderType::Market(candle.close()), 1.0, OrderSide::Buy)))?;
+            // the next candle trades through the limit price
+            bt.place_order(Order::from((OrderType::Limit(101.0), 1.0, OrderSide::Buy)))?;
+        } else {
+            assert_eq!(bt.positions.len(), 0);
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    let entry_prices = bt.positions().map(|p| p.entry_price().unwrap()).collect::<Vec<_>>();
+    assert_eq!(entry_prices, vec![108.0, 101.0]);
+    assert_eq!(bt.balance(), 791.0);
+    assert_eq!(bt.free_balance().unwrap(), 791.0);
+}
+
+#[test]
+fn scenario_next_open_insufficient_funds() {
+    let data = get_gap_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_execution_model(ExecutionModel::NextOpen);
+
+    bt.run(|bt, candle| {
+        if bt.index == 0 {
+            // 9.5 * 108 is more than the balance
+            bt.place_order(Order::from((OrderType::Market(candle.close()), 9.5, OrderSide::Buy)))?;
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    assert!(bt.positions.is_empty());
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.free_balance().unwrap(), 1000.0);
+}
+
+#[test]
+fn scenario_same_close_execution() {
+    let data = get_gap_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_execution_model(ExecutionModel::SameClose);
+
+    bt.run(|bt, _| {
+        if bt.index == 0 {
+            // low = 95, the stop-loss is not checked on the entry candle
+            let exit_rule = OrderType::TakeProfitAndStopLoss(0.0, 96.0);
+            bt.place_order(Order::from((OrderType::Market(104.0), exit_rule, 1.0, OrderSide::Buy)))?;
+            // the limit price is only matched from the next candle
+            bt.place_order(Order::from((OrderType::Limit(97.0), 1.0, OrderSide::Buy)))?;
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    assert_eq!(bt.positions.len(), 1);
+    assert_eq!(bt.positions().next().unwrap().entry_price().unwrap(), 105.0);
+    assert_eq!(bt.orders.len(), 1);
+    assert_eq!(bt.balance(), 895.0);
+}
+
+#[test]
+fn scenario_current_bar_execution() {
+    let data = get_gap_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None).unwrap();
+    assert_eq!(bt.execution_model(), &ExecutionModel::CurrentBar);
+
+    bt.run(|bt, _| {
+        if bt.index == 0 {
+            let exit_rule = OrderType::TakeProfitAndStopLoss(0.0, 96.0);
+            bt.place_order(Order::from((OrderType::Market(104.0), exit_rule, 1.0, OrderSide::Buy)))?;
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    // filled at 104 and stopped at 96 inside the same candle
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 992.0);
+}
+
+#[test]
+fn scenario_gap_stop_loss_fill() {
+    let data = get_gap_data();
+    let mut bt = Backtest::new(data, 1000.0, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::TakeProfitAndStopLoss(0.0, 107.0);
+    let order = Order::from((OrderType::Market(105.0), exit_rule, 1.0, OrderSide::Sell));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // the candle opens at 108 above the stop-loss
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 997.0);
+
+    #[cfg(feature = "metrics")]
+    {
+        let exit_price = bt.events().find_map(|e| match e {
+            Event::DelPosition(position) => position.exit_price(),
+            _ => None,
+        });
+        assert_eq!(exit_price, Some(108.0));
+    }
+}
+
+#[test]
+fn scenario_gap_take_profit_fill() {
+    let data = get_gap_data();
+    let mut bt = Backtest::new(data, 1000.0, None).unwrap();
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::TakeProfitAndStopLoss(106.0, 0.0);
+    let order = Order::from((OrderType::Market(105.0), exit_rule, 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // the candle opens at 108 above the take-profit
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 1003.0);
+}
+
+#[test]
+fn scenario_gap_entry_fills() {
+    let data = get_gap_data();
+    let mut bt = Backtest::new(data, 1000.0, None).unwrap();
+    bt.next().unwrap();
+
+    // the candle opens at 108, better than the buy limit and worse than the buy stop
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Limit(110.0), 1.0, OrderSide::Buy)))
+        .unwrap();
+    bt.place_order(Order::from((OrderType::Stop(106.0), 1.0, OrderSide::Buy)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    let entry_prices = bt.positions().map(|p| p.entry_price().unwrap()).collect::<Vec<_>>();
+    assert_eq!(entry_prices, vec![108.0, 108.0]);
+    assert_eq!(bt.balance(), 784.0);
+    assert_eq!(bt.free_balance().unwrap(), 784.0);
+}
+
+#[test]
+fn scenario_slippage_on_market_entry() {
+    let data = get_gap_data();
+    let mut bt = Backtest::new(data, 1000.0, None)
+        .unwrap()
+        .with_slippage(FixedTicks(2, 0.5));
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::TakeProfitAndStopLoss(111.0, 0.0);
+    let order = Order::from((OrderType::Market(105.0), exit_rule, 2.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    // limit orders are not slipped
+    bt.place_order(Order::from((OrderType::Limit(100.0), 1.0, OrderSide::Buy)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    let entry_prices = bt.positions().map(|p| p.entry_price().unwrap()).collect::<Vec<_>>();
+    assert_eq!(entry_prices, vec![106.0, 100.0]);
+    assert_eq!(bt.balance(), 688.0);
+    assert_eq!(bt.free_balance().unwrap(), 688.0);
+    assert_eq!(bt.slippage_paid(), 2.0);
+
+    // take-profits are not slipped
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert_eq!(bt.positions.len(), 1);
+    assert_eq!(bt.balance(), 910.0); // 688 + 212 + 10
+    assert_eq!(bt.slippage_paid(), 2.0);
+}
+
+#[test]
+fn scenario_slippage_on_stop_exit() {
+    let data = get_gap_data();
+    let mut bt = Backtest::new(data, 1000.0, None)
+        .unwrap()
+        .with_slippage(FixedTicks(2, 0.5));
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::TakeProfitAndStopLoss(0.0, 107.0);
+    let order = Order::from((OrderType::Market(105.0), exit_rule, 1.0, OrderSide::Sell));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // the stop-loss is filled at the open (108), then slipped
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 995.0); // 104 - 109
+    assert_eq!(bt.slippage_paid(), 2.0);
+    assert_eq!(bt.fees_paid(), 0.0);
+
+    #[cfg(feature = "metrics")]
+    {
+        let slippage = bt.events().rev().find_map(|e| match e {
+            Event::WalletUpdate { slippage, .. } => Some(*slippage),
+            _ => None,
+        });
+        assert_eq!(slippage, Some(2.0));
+    }
+}
+
+#[test]
+fn scenario_partial_fills_across_candles() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    // candles have a volume of 1
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_participation_rate(0.5)
+        .unwrap()
+        .with_max_pyramids(1);
+
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Limit(90.0), 1.0, OrderSide::Buy)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.positions().next().unwrap().quantity, 0.5);
+    assert_eq!(bt.orders().next().unwrap().quantity, 0.5);
+    assert_eq!(bt.balance(), 955.0);
+    assert_eq!(bt.free_balance().unwrap(), 910.0);
+
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // the partial fills are merged into a single position
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.positions.len(), 1);
+    let position = bt.positions().next().unwrap();
+    assert_eq!(position.quantity, 1.0);
+    assert_eq!(position.entry_price().unwrap(), 90.0);
+    assert_eq!(position.entries(), 1);
+    assert_eq!(bt.balance(), 910.0);
+    assert_eq!(bt.free_balance().unwrap(), 910.0);
+
+    // the remaining quantity does not count as a pyramid
+    let id = position.id();
+    let order = Order::from((OrderType::Limit(80.0), 1.0, OrderSide::Buy));
+    bt.add_to_position(id, order).unwrap();
+    let order = Order::from((OrderType::Limit(80.0), 1.0, OrderSide::Buy));
+    let result = bt.add_to_position(id, order);
+    assert!(matches!(result, Err(Error::MaxPyramid(1))));
+
+    #[cfg(feature = "metrics")]
+    {
+        let partial_fills = bt.events().filter(|e| matches!(e, Event::PartialFillOrder(_))).count();
+        assert_eq!(partial_fills, 1);
+    }
+}
+
+#[test]
+fn scenario_partial_fill_time_in_force() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_participation_rate(0.5)
+        .unwrap();
+
+    // immediate-or-cancel: the remaining quantity expires
+    let candle = bt.next().unwrap();
+    let mut order = Order::from((OrderType::Market(100.0), 2.0, OrderSide::Buy));
+    order.set_time_in_force(TimeInForce::ImmediateOrCancel);
+    bt.place_order(order).unwrap();
+    // fill-or-kill: the order expires as it can not be entirely filled
+    let mut order = Order::from((OrderType::Limit(100.0), 2.0, OrderSide::Buy));
+    order.set_time_in_force(TimeInForce::FillOrKill);
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.positions.len(), 1);
+    assert_eq!(bt.positions().next().unwrap().quantity, 0.5);
+    assert_eq!(bt.balance(), 950.0);
+    assert_eq!(bt.free_balance().unwrap(), 950.0);
+}
+
+#[test]
+fn scenario_partial_fill_market_order() {
+    let data = get_long_data();
+    let balance = 1000.0;
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_participation_rate(0.5)
+        .unwrap();
+
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Market(100.0), 1.5, OrderSide::Buy)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.positions().next().unwrap().quantity, 0.5);
+    assert_eq!(bt.orders().next().unwrap().quantity, 1.0);
+    assert_eq!(
+        bt.orders().next().unwrap().time_in_force(),
+        &TimeInForce::GoodTillCancelled
+    );
+
+    // open = 100, then open = 110
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.positions.len(), 1);
+    let position = bt.positions().next().unwrap();
+    assert_eq!(position.quantity, 1.5);
+    assert_eq!(position.entries(), 1);
+    assert_eq!(bt.balance(), 845.0);
+    assert_eq!(bt.free_balance().unwrap(), 845.0);
+}
+
+#[test]
+fn scenario_partial_fill_stop_order() {
+    let data = [(110.0, 125.0, 105.0, 115.0), (100.0, 105.0, 95.0, 100.0)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (open, high, low, close))| {
+            let open_time = DateTime::from_timestamp_secs(1515151515 + i as i64).unwrap();
+            CandleBuilder::builder()
+                .open(open)
+                .high(high)
+                .low(low)
+                .close(close)
+                .volume(10.0)
+                .open_time(open_time)
+                .close_time(open_time + chrono::TimeDelta::seconds(1))
+                .build()
+                .unwrap()
+        })
+        .collect();
+    let mut bt = Backtest::new(data, 1000.0, None)
+        .unwrap()
+        .with_participation_rate(0.1)
+        .unwrap();
+
+    // high = 125, triggered at 120 and filled for 1 unit
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Stop(120.0), 2.0, OrderSide::Buy)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert_eq!(bt.positions().next().unwrap().quantity, 1.0);
+    assert_eq!(bt.balance(), 880.0);
+
+    // the candle trades between 95 and 105, the remaining unit is filled at the open
+    let candle = bt.next().unwrap();
+    bt.execute_orders(&candle).unwrap();
+    assert!(bt.orders.is_empty());
+    let position = bt.positions().next().unwrap();
+    assert_eq!(position.quantity, 2.0);
+    assert_eq!(position.entry_price().unwrap(), 110.0);
+    assert_eq!(bt.balance(), 780.0);
+    assert_eq!(bt.free_balance().unwrap(), 780.0);
+}
+
+#[test]
+fn scenario_invalid_participation_rate() {
+    let data = get_long_data();
+    let result = Backtest::new(data, 1000.0, None).unwrap().with_participation_rate(1.5);
+    assert!(matches!(result, Err(Error::InvalidFraction(1.5))));
+}
+
+#[test]
+fn scenario_latency_in_bars() {
+    let data = get_long_data();
+    let mut bt = Backtest::new(data, 1000.0, None)
+        .unwrap()
+        .with_latency(Latency::Bars(1));
+
+    bt.run(|bt, candle| {
+        if candle.open() == 90.0 {
+            bt.place_order(Order::from((OrderType::Limit(95.0), 1.0, OrderSide::Buy)))
+                .unwrap();
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    // the order reaches the market on the second candle, which does not gap through the limit
+    let position = bt.positions().next().unwrap();
+    assert_eq!(position.entry_index(), 1);
+    assert_eq!(position.entry_price().unwrap(), 95.0);
+}
+
+fn get_timed_data() -> Vec<Candle> {
+    get_long_data()
+        .iter()
+        .enumerate()
+        .map(|(i, candle)| {
+            let open_time = DateTime::from_timestamp_secs(1515151500 + 60 * i as i64).unwrap();
+            CandleBuilder::builder()
+                .open(candle.open())
+                .high(candle.high())
+                .low(candle.low())
+                .close(candle.close())
+                .volume(candle.volume())
+                .open_time(open_time)
+                .close_time(open_time + chrono::TimeDelta::seconds(60))
+                .build()
+                .unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn scenario_latency_in_duration() {
+    let data = get_timed_data();
+    let mut bt = Backtest::new(data, 1000.0, None)
+        .unwrap()
+        .with_latency(Latency::Duration(std::time::Duration::from_secs(90)));
+
+    bt.run(|bt, candle| {
+        if candle.open() == 90.0 {
+            bt.place_order(Order::from((OrderType::Limit(105.0), 1.0, OrderSide::Buy)))
+                .unwrap();
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    // the first candle closing 90 seconds after the order is the third one
+    let position = bt.positions().next().unwrap();
+    assert_eq!(position.entry_index(), 2);
+    assert_eq!(position.entry_price().unwrap(), 105.0);
+}
+
+#[test]
+fn scenario_latency_overflow() {
+    for latency in [
+        Latency::Duration(std::time::Duration::from_secs(u64::MAX)),
+        Latency::Bars(usize::MAX),
+    ] {
+        let data = get_timed_data();
+        let mut bt = Backtest::new(data, 1000.0, None).unwrap().with_latency(latency);
+
+        bt.run(|bt, candle| {
+            if candle.open() == 90.0 {
+                bt.place_order(Order::from((OrderType::Limit(105.0), 1.0, OrderSide::Buy)))
+                    .unwrap();
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        // the order never reaches the market
+        assert!(bt.positions.is_empty());
+        assert_eq!(bt.orders.len(), 1);
+    }
+}
+
+#[test]
+fn scenario_funding_series() {
+    let data = get_timed_data();
+    let balance = 1000.0;
+    let funding_time = data[1].close_time();
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_funding(Funding::Series(vec![(funding_time, 0.01)]));
+
+    bt.run(|bt, candle| {
+        if candle.open() == 90.0 {
+            bt.place_order(Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy)))
+                .unwrap();
+            bt.place_order(Order::from((OrderType::Market(100.0), 2.0, OrderSide::Sell)))
+                .unwrap();
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    // the long pays 0.01 × 110 and the short of 2 receives twice as much
+    assert_eq!(bt.funding_paid(), -1.1);
+    assert_eq!(bt.balance(), 1000.0 - 100.0 + 200.0 + 1.1);
+
+    #[cfg(feature = "metrics")]
+    {
+        let metrics = Metrics::from(&bt);
+        assert_eq!(metrics.funding_paid(), -1.1);
+    }
+}
+
+#[test]
+fn scenario_funding_constant() {
+    let data = get_timed_data();
+    let balance = 1000.0;
+    // candles close every minute
+    let mut bt = Backtest::new(data, balance, None)
+        .unwrap()
+        .with_funding(Funding::Constant(0.01, std::time::Duration::from_secs(60)));
+
+    bt.run(|bt, candle| {
+        if candle.open() == 90.0 {
+            bt.place_order(Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy)))
+                .unwrap();
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    // paid at the close of each candle: 0.01 × (100 + 110 + 120)
+    assert_eq!(bt.funding_paid(), 3.3);
+}
+
+#[test]
+fn scenario_short_borrow_fee() {
+    let open_time = DateTime::from_timestamp_secs(1515151515).unwrap();
+    let candle = CandleBuilder::builder()
+        .open(100.0)
+        .high(111.0)
+        .low(99.0)
+        .close(110.0)
+        .volume(1.0)
+        .open_time(open_time)
+        .close_time(open_time + chrono::TimeDelta::days(365))
+        .build()
+        .unwrap();
+    let mut bt = Backtest::new(vec![candle], 1000.0, None)
+        .unwrap()
+        .with_borrow_fee(0.25)
+        .unwrap();
+
+    bt.run(|bt, _candle| {
+        bt.place_order(Order::from((OrderType::Market(110.0), 1.0, OrderSide::Sell)))
+            .unwrap();
+        bt.place_order(Order::from((OrderType::Market(110.0), 1.0, OrderSide::Buy)))
+            .unwrap();
+        Ok(())
+    })
+    .unwrap();
+
+    // a year of borrowing on a notional of 110, the long pays nothing
+    assert_eq!(bt.borrow_paid(), 27.5);
+    assert_eq!(bt.fees_paid(), 0.0);
+    assert_eq!(bt.balance(), 1000.0 + 110.0 - 110.0 - 27.5);
+
+    #[cfg(feature = "metrics")]
+    {
+        let metrics = Metrics::from(&bt);
+        assert_eq!(metrics.borrow_paid(), 27.5);
+    }
+}
+
+#[test]
+fn scenario_invalid_borrow_fee() {
+    for rate in [-0.05, f64::NAN, f64::INFINITY] {
+        let result = Backtest::new(get_data(), 1000.0, None).unwrap().with_borrow_fee(rate);
+        assert!(matches!(result, Err(Error::InvalidRate(_))));
+    }
+    assert!(
+        Backtest::new(get_data(), 1000.0, None)
+            .unwrap()
+            .with_borrow_fee(0.0)
+            .is_ok()
+    );
+}
+
+#[test]
+fn scenario_hard_to_borrow() {
+    let data = get_long_data();
+    let mut bt = Backtest::new(data, 1000.0, None)
+        .unwrap()
+        .with_account_mode(AccountMode::OneWay)
+        .with_hard_to_borrow(true);
+
+    let result = bt.place_order(Order::from((OrderType::Market(100.0), 1.0, OrderSide::Sell)));
+    assert!(matches!(result, Err(Error::HardToBorrow)));
+    assert_eq!(bt.free_balance().unwrap(), 1000.0);
+
+    let candle = bt.next().unwrap();
+    bt.place_order(Order::from((OrderType::Market(100.0), 1.0, OrderSide::Buy)))
+        .unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // selling the long is allowed, but not selling more than it
+    let result = bt.place_order(Order::from((OrderType::Market(100.0), 2.0, OrderSide::Sell)));
+    assert!(matches!(result, Err(Error::HardToBorrow)));
+    bt.place_order(Order::from((OrderType::Market(100.0), 1.0, OrderSide::Sell)))
+        .unwrap();
+}
+
+#[test]
+fn scenario_latency_delays_cancel() {
+    let data = get_long_data();
+    let mut bt = Backtest::new(data, 1000.0, None)
+        .unwrap()
+        .with_latency(Latency::Bars(1));
+
+    bt.run(|bt, candle| {
+        match candle.open() {
+            90.0 => {
+                bt.place_order(Order::from((OrderType::Limit(85.0), 1.0, OrderSide::Buy)))
+                    .unwrap();
+                bt.place_order(Order::from((OrderType::Limit(95.0), 1.0, OrderSide::Buy)))
+                    .unwrap();
+            }
+            100.0 => {
+                let orders = bt.orders().cloned().collect::<Vec<_>>();
+                for order in orders {
+                    bt.delete_order(&order, true).unwrap();
+                }
+            }
+            _ => {
+                // the cancel of the unfilled order has not reached the market yet
+                assert_eq!(bt.orders().count(), 1);
+            }
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    // the limit at 95 is filled before its cancel reaches the market
+    assert!(bt.orders.is_empty());
+    assert_eq!(bt.positions.len(), 1);
+    assert_eq!(bt.positions().next().unwrap().entry_price().unwrap(), 95.0);
+    assert_eq!(bt.free_balance().unwrap(), 905.0);
+}
+
+fn run_intrabar_path(intrabar_path: IntrabarPath) -> f64 {
+    let data = get_long_data();
+    let mut bt = Backtest::new(data, 1000.0, None)
+        .unwrap()
+        .with_intrabar_path(intrabar_path);
+
+    let candle = bt.next().unwrap();
+    let exit_rule = OrderType::TakeProfitAndStopLoss(115.0, 95.0);
+    let order = Order::from((OrderType::Market(100.0), exit_rule, 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // high = 119 and low = 90, both exit prices are reached
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    bt.balance()
+}
+
+#[test]
+fn scenario_intrabar_path() {
+    assert_eq!(run_intrabar_path(IntrabarPath::Optimistic), 1015.0);
+    assert_eq!(run_intrabar_path(IntrabarPath::Pessimistic), 995.0);
+    // open = 100, the low is nearer than the high
+    assert_eq!(run_intrabar_path(IntrabarPath::OhlcHeuristic), 995.0);
+
+    let lower_candle = CandleBuilder::builder()
+        .open(100.0)
+        .high(116.0)
+        .low(99.0)
+        .close(115.0)
+        .volume(1.0)
+        .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
+        .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
+        .build()
+        .unwrap();
+    let intrabar_path = IntrabarPath::LowerTimeframe(vec![lower_candle]);
+    assert_eq!(run_intrabar_path(intrabar_path), 1015.0);
+}
+
+#[test]
+fn scenario_intrabar_path_ladder() {
+    let data = get_short_data();
+    let mut bt = Backtest::new(data, 1000.0, None)
+        .unwrap()
+        .with_intrabar_path(IntrabarPath::Pessimistic);
+
+    let candle = bt.next().unwrap();
+    let ladder = OrderType::TakeProfitLadder(vec![(150.0, 0.5)], 125.0);
+    let order = Order::from((OrderType::Market(140.0), ladder, 1.0, OrderSide::Buy));
+    bt.place_order(order).unwrap();
+    bt.execute_orders(&candle).unwrap();
+
+    // high = 150 and low = 121, the whole position is stopped first
+    let candle = bt.next().unwrap();
+    bt.execute_positions(&candle).unwrap();
+    assert!(bt.positions.is_empty());
+    assert_eq!(bt.balance(), 985.0);
+}
+
 struct TestAggregator;
 
 impl Aggregation for TestAggregator {
@@ -604,7 +2686,7 @@ fn scenario_with_aggregator() {
     let mut ic = 0;
     let aggregator = TestAggregator;
     bt.run_with_aggregator(&aggregator, |_, candles| {
-        let candle_one = candles.get(0);
+        let candle_one = candles.first();
         let candle_two = candles.get(1);
 
         // candle_two is none at ic = 0
@@ -0,0 +1,130 @@
+use super::order::{Liquidity, OrderSide};
+
+/// Trait for modeling the fees charged on each fill (entries, additions and exits).
+///
+/// A negative fee is a rebate credited to the wallet.
+pub trait FeeModel: std::fmt::Debug + Send + Sync {
+    /// Returns the fee of a fill.
+    ///
+    /// ### Arguments
+    /// * `price` - The fill price.
+    /// * `quantity` - The filled quantity.
+    /// * `side` - The side of the fill (exits of long positions are sells).
+    /// * `liquidity` - Whether the fill adds or removes liquidity.
+    /// * `volume` - The notional traded before this fill.
+    fn fee(&self, price: f64, quantity: f64, side: &OrderSide, liquidity: Liquidity, volume: f64) -> f64;
+}
+
+/// Fees as fractions of the notional, for the fills adding and removing liquidity.
+///
+/// ### Arguments
+/// * `0` - The maker fee (e.g., 0.001 for 0.1%, negative for a rebate).
+/// * `1` - The taker fee.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct MakerTaker(pub f64, pub f64);
+
+impl FeeModel for MakerTaker {
+    fn fee(&self, price: f64, quantity: f64, _side: &OrderSide, liquidity: Liquidity, _volume: f64) -> f64 {
+        match liquidity {
+            Liquidity::Maker => price * quantity * self.0,
+            Liquidity::Taker => price * quantity * self.1,
+        }
+    }
+}
+
+/// Maker/taker fees decreasing with the notional traded.
+///
+/// The tier applied is the one with the highest volume threshold reached.
+/// No fee is charged below the lowest threshold.
+///
+/// ### Arguments
+/// * `0` - The tiers, as volume thresholds with their fees.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct TieredFees(pub Vec<(f64, MakerTaker)>);
+
+impl FeeModel for TieredFees {
+    fn fee(&self, price: f64, quantity: f64, side: &OrderSide, liquidity: Liquidity, volume: f64) -> f64 {
+        self.0
+            .iter()
+            .filter(|(threshold, _)| *threshold <= volume)
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map_or(0.0, |(_, fees)| fees.fee(price, quantity, side, liquidity, volume))
+    }
+}
+
+/// Commission per share, bounded per fill (e.g., equity brokers).
+///
+/// The bounds apply to each fill, not to the whole order: an order filled in several parts
+/// (participation rate, take-profit ladder) pays the minimum commission on each part.
+///
+/// ### Arguments
+/// * `0` - The commission per share (e.g., 0.005).
+/// * `1` - The minimum commission (e.g., 1.0).
+/// * `2` - The maximum commission (`f64::INFINITY` for none).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct PerShare(pub f64, pub f64, pub f64);
+
+impl FeeModel for PerShare {
+    fn fee(&self, _price: f64, quantity: f64, _side: &OrderSide, _liquidity: Liquidity, _volume: f64) -> f64 {
+        (quantity * self.0).max(self.1).min(self.2)
+    }
+}
+
+/// Regulatory fees charged on sells only (e.g., the SEC fee and the FINRA trading activity fee).
+///
+/// ### Arguments
+/// * `0` - The fee as a fraction of the sold notional (e.g., 0.0000278).
+/// * `1` - The fee per share sold (e.g., 0.000166).
+/// * `2` - The maximum of the fee per share (`f64::INFINITY` for none).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct SellFees(pub f64, pub f64, pub f64);
+
+impl FeeModel for SellFees {
+    fn fee(&self, price: f64, quantity: f64, side: &OrderSide, _liquidity: Liquidity, _volume: f64) -> f64 {
+        match side {
+            OrderSide::Buy => 0.0,
+            OrderSide::Sell => price * quantity * self.0 + (quantity * self.1).min(self.2),
+        }
+    }
+}
+
+/// Charges both fee models, e.g., a broker commission with the regulatory fees.
+impl<A: FeeModel, B: FeeModel> FeeModel for (A, B) {
+    fn fee(&self, price: f64, quantity: f64, side: &OrderSide, liquidity: Liquidity, volume: f64) -> f64 {
+        self.0.fee(price, quantity, side, liquidity, volume) + self.1.fee(price, quantity, side, liquidity, volume)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn builtin_fee_models() {
+    let (buy, sell) = (OrderSide::Buy, OrderSide::Sell);
+    let fees = MakerTaker(-0.001, 0.002);
+    assert_eq!(fees.fee(100.0, 10.0, &buy, Liquidity::Maker, 0.0), -1.0);
+    assert_eq!(fees.fee(100.0, 10.0, &buy, Liquidity::Taker, 0.0), 2.0);
+
+    let fees = TieredFees(vec![
+        (10_000.0, MakerTaker(0.0, 0.001)),
+        (0.0, MakerTaker(0.001, 0.002)),
+    ]);
+    assert_eq!(fees.fee(100.0, 10.0, &buy, Liquidity::Taker, 0.0), 2.0);
+    assert_eq!(fees.fee(100.0, 10.0, &buy, Liquidity::Taker, 10_000.0), 1.0);
+    assert_eq!(fees.fee(100.0, 10.0, &buy, Liquidity::Maker, 20_000.0), 0.0);
+
+    let fees = PerShare(0.005, 1.0, 5.0);
+    assert_eq!(fees.fee(100.0, 100.0, &buy, Liquidity::Taker, 0.0), 1.0);
+    assert_eq!(fees.fee(100.0, 400.0, &buy, Liquidity::Taker, 0.0), 2.0);
+    assert_eq!(fees.fee(100.0, 2000.0, &buy, Liquidity::Taker, 0.0), 5.0);
+
+    let fees = SellFees(0.0001, 0.01, 0.5);
+    assert_eq!(fees.fee(100.0, 10.0, &buy, Liquidity::Taker, 0.0), 0.0);
+    assert_eq!(fees.fee(100.0, 10.0, &sell, Liquidity::Taker, 0.0), 0.2);
+    assert_eq!(fees.fee(100.0, 100.0, &sell, Liquidity::Taker, 0.0), 1.5);
+
+    let fees = (PerShare(0.005, 1.0, 5.0), SellFees(0.0001, 0.01, 0.5));
+    assert_eq!(fees.fee(100.0, 100.0, &sell, Liquidity::Taker, 0.0), 2.5);
+}
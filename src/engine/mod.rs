@@ -3,29 +3,41 @@
 //! This module provides the fundamental types for backtesting:
 //! - `Order`: Market, limit, and conditional orders.
 //! - `Position`: Open trades with exit rules.
-//! - `Wallet`: Tracks balance, fees, and P&L.
+//! - `Wallet`: Tracks balance, fees, slippage, funding, borrow fees, and P&L.
+//! - `Slippage`: Models the adverse price move of market fills.
+//! - `FeeModel`: Models the fees charged on each fill.
 //! - `Candle`: OHLCV data for backtesting.
 
 mod candle;
+mod fees;
 mod order;
 mod position;
+mod slippage;
 mod wallet;
 
 use std::collections::{VecDeque, vec_deque::Iter};
 
+use chrono::{DateTime, Utc};
+
 use crate::{
     PercentCalculus,
     errors::{Error, Result},
+    utils::IdGenerator,
 };
 
 #[cfg(feature = "metrics")]
 use crate::metrics::*;
 
 pub use candle::*;
+pub use fees::*;
 pub use order::*;
 pub use position::*;
+pub use slippage::*;
 pub(crate) use wallet::*;
 
+/// Relative tolerance when comparing a closed quantity with the quantity of a position.
+const QUANTITY_EPSILON: f64 = 1e-9;
+
 #[cfg(test)]
 mod bts;
 
@@ -87,13 +99,30 @@ pub trait Aggregation {
 #[derive(Debug)]
 pub struct Backtest {
     index: usize,
+    ids: IdGenerator,
     wallet: Wallet,
     data: Vec<Candle>,
     #[cfg(feature = "metrics")]
     events: Vec<Event>,
     orders: VecDeque<Order>,
     positions: VecDeque<Position>,
-    market_fees: Option<(f64, f64)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    fee_model: Option<Box<dyn FeeModel>>,
+    volume: f64,
+    account_mode: AccountMode,
+    max_pyramids: Option<usize>,
+    execution_model: ExecutionModel,
+    intrabar_path: IntrabarPath,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    slippage: Option<Box<dyn Slippage>>,
+    participation_rate: Option<f64>,
+    latency: Option<Latency>,
+    leverage: f64,
+    margin: Option<(f64, f64)>,
+    funding: Option<Funding>,
+    funding_time: Option<DateTime<Utc>>,
+    borrow_rate: f64,
+    hard_to_borrow: bool,
 }
 
 impl std::ops::Deref for Backtest {
@@ -110,14 +139,14 @@ impl Backtest {
     /// ### Arguments
     /// * `data` - Vector of candle data.
     /// * `initial_balance` - Initial wallet balance.
-    /// * `market_fee` - Market *(market and limit)* fee percentage (e.g., 0.1 for 0.1%).
-    ///   Fees are **only applied when positions are opened**, not when orders are placed.
+    /// * `market_fees` - Optional tuple of (taker fee, maker fee) as fractions of the notional
+    ///   (e.g., 0.001 for 0.1%), a shorthand for `with_fee_model(MakerTaker(maker, taker))`.
+    ///   The maker fee can be negative (a rebate), not the taker fee.
     ///
     /// ### Market Fees Behavior
     /// - **Order Placement**: No fees are charged when placing an order.
-    ///   Fees are only deducted when the order is executed and a position is opened.
-    /// - **Position Opening**: Fees are calculated as `price × quantity × market_fee`
-    ///   and deducted from the wallet when the position is opened.
+    /// - **Fills**: Fees are calculated as `price × quantity × fee` at each fill (entries, additions
+    ///   and exits), with the maker or taker fee of the fill, and deducted from the wallet.
     /// - **Order Cancellation**: No fees are charged if an order is cancelled before execution.
     ///
     /// ### Returns
@@ -127,16 +156,32 @@ impl Backtest {
             return Err(Error::CandleDataEmpty);
         }
 
-        if let Some((market_fee, limit_fee)) = market_fees {
-            if market_fee <= 0.0 || limit_fee <= 0.0 {
-                return Err(Error::NegZeroFees);
-            }
+        if let Some((market_fee, limit_fee)) = market_fees
+            && (market_fee < 0.0 || !market_fee.is_finite() || !limit_fee.is_finite())
+        {
+            return Err(Error::NegZeroFees);
         }
 
         Ok(Self {
             data,
             index: 0,
-            market_fees,
+            ids: IdGenerator::default(),
+            fee_model: market_fees
+                .map(|(market_fee, limit_fee)| Box::new(MakerTaker(limit_fee, market_fee)) as Box<dyn FeeModel>),
+            volume: 0.0,
+            account_mode: AccountMode::default(),
+            max_pyramids: None,
+            execution_model: ExecutionModel::default(),
+            intrabar_path: IntrabarPath::default(),
+            slippage: None,
+            participation_rate: None,
+            latency: None,
+            leverage: 1.0,
+            margin: None,
+            funding: None,
+            funding_time: None,
+            borrow_rate: 0.0,
+            hard_to_borrow: false,
             #[cfg(feature = "metrics")]
             events: Vec::new(),
             orders: VecDeque::new(),
@@ -145,6 +190,218 @@ impl Backtest {
         })
     }
 
+    /// Sets how filled orders are turned into positions.
+    ///
+    /// ### Arguments
+    /// * `account_mode` - Hedge mode *(default)* or one-way netting mode.
+    ///
+    /// ### Returns
+    /// The backtest instance.
+    pub fn with_account_mode(mut self, account_mode: AccountMode) -> Self {
+        self.account_mode = account_mode;
+        self
+    }
+
+    /// Returns how filled orders are turned into positions.
+    pub fn account_mode(&self) -> &AccountMode {
+        &self.account_mode
+    }
+
+    /// Sets the maximum number of times a position can be added to with `add_to_position`.
+    ///
+    /// ### Arguments
+    /// * `max_pyramids` - The maximum pyramid count *(unlimited by default)*.
+    ///
+    /// ### Returns
+    /// The backtest instance.
+    pub fn with_max_pyramids(mut self, max_pyramids: usize) -> Self {
+        self.max_pyramids = Some(max_pyramids);
+        self
+    }
+
+    /// Sets when and at which price the orders placed by the strategy are filled.
+    ///
+    /// ### Arguments
+    /// * `execution_model` - Current bar *(default)*, same bar close or next bar open.
+    ///
+    /// ### Returns
+    /// The backtest instance.
+    pub fn with_execution_model(mut self, execution_model: ExecutionModel) -> Self {
+        self.execution_model = execution_model;
+        self
+    }
+
+    /// Returns when and at which price the orders placed by the strategy are filled.
+    pub fn execution_model(&self) -> &ExecutionModel {
+        &self.execution_model
+    }
+
+    /// Sets the assumed price path inside a candle reaching both the take-profit and the stop-loss.
+    ///
+    /// Also decides which leg of a bracket group is filled when the candle reaches both.
+    ///
+    /// ### Arguments
+    /// * `intrabar_path` - Optimistic *(default)*, pessimistic, OHLC heuristic or lower-timeframe candles.
+    ///
+    /// ### Returns
+    /// The backtest instance.
+    pub fn with_intrabar_path(mut self, intrabar_path: IntrabarPath) -> Self {
+        self.intrabar_path = intrabar_path;
+        self
+    }
+
+    /// Returns the assumed price path inside a candle reaching both the take-profit and the stop-loss.
+    pub fn intrabar_path(&self) -> &IntrabarPath {
+        &self.intrabar_path
+    }
+
+    /// Sets the slippage model applied to the fills removing liquidity.
+    ///
+    /// ### Arguments
+    /// * `slippage` - The slippage model (e.g., `FixedBps(5.0)`). There is no slippage by default.
+    ///
+    /// ### Returns
+    /// The backtest instance.
+    pub fn with_slippage<S: Slippage + 'static>(mut self, slippage: S) -> Self {
+        self.slippage = Some(Box::new(slippage));
+        self
+    }
+
+    /// Limits the quantity filled on a candle to a part of its volume.
+    ///
+    /// Larger orders are partially filled across several candles, and stay in the pending orders
+    /// with their remaining quantity. The remaining quantity of a market or a triggered stop order is
+    /// filled at the open of the next candles, unless immediate-or-cancel is set explicitly: such orders expire after
+    /// their first partial fill. Fill-or-kill orders expire if they can not be entirely filled.
+    ///
+    /// ### Arguments
+    /// * `participation_rate` - The maximum fraction of the candle volume (e.g., 0.1 for 10%).
+    ///
+    /// ### Returns
+    /// The backtest instance, or an error if the rate is not in ]0, 1].
+    pub fn with_participation_rate(mut self, participation_rate: f64) -> Result<Self> {
+        if participation_rate <= 0.0 || participation_rate > 1.0 {
+            return Err(Error::InvalidFraction(participation_rate));
+        }
+        self.participation_rate = Some(participation_rate);
+        Ok(self)
+    }
+
+    /// Delays the orders and cancels sent by the strategy.
+    ///
+    /// Placed orders are only matched once they reach the market, and deleted orders
+    /// can still be filled until the cancel reaches the market.
+    /// Child orders placed by the backtest (e.g., bracket legs) are not delayed.
+    ///
+    /// ### Arguments
+    /// * `latency` - The delay in candles or in time.
+    ///
+    /// ### Returns
+    /// The backtest instance.
+    pub fn with_latency(mut self, latency: Latency) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Trades with leverage, liquidating the positions when the equity falls below the maintenance margin.
+    ///
+    /// Orders lock the initial margin `price × quantity / leverage` instead of the whole notional.
+    /// At each candle, the equity (balance, posted margin and unrealized P&L) at the worst price reached
+    /// by the positions (the low for a long, the high for a short) is compared to the maintenance margin.
+    /// Below it, the pending orders are cancelled and all the positions are closed at their liquidation
+    /// price (or the open beyond it), paying the liquidation fee. When a gap leaves
+    /// a loss larger than the posted margin and the free balance, the balance is floored at zero
+    /// and the uncovered loss is recorded as the shortfall.
+    ///
+    /// ### Arguments
+    /// * `leverage` - The leverage (e.g., 10.0), the initial margin fraction being its inverse.
+    /// * `maintenance_margin` - The maintenance margin as a fraction of the notional (e.g., 0.005),
+    ///   lower than the initial margin fraction.
+    /// * `liquidation_fee` - The liquidation fee as a fraction of the notional (e.g., 0.01).
+    ///
+    /// ### Returns
+    /// The backtest instance, or an error if the leverage or a margin fraction is invalid.
+    pub fn with_leverage(mut self, leverage: f64, maintenance_margin: f64, liquidation_fee: f64) -> Result<Self> {
+        if !leverage.is_finite() || leverage < 1.0 {
+            return Err(Error::InvalidMargin(leverage));
+        }
+        if !(0.0..1.0 / leverage).contains(&maintenance_margin) {
+            return Err(Error::InvalidMargin(maintenance_margin));
+        }
+        if !(0.0..=1.0).contains(&liquidation_fee) {
+            return Err(Error::InvalidMargin(liquidation_fee));
+        }
+        self.leverage = leverage;
+        self.margin = Some((maintenance_margin, liquidation_fee));
+        Ok(self)
+    }
+
+    /// Charges a borrow fee to the short positions.
+    ///
+    /// The fee is accrued at each candle close on the positions still open, as
+    /// `annual_rate × quantity × close × candle duration / 365 days`, and tracked apart from the
+    /// trading fees (see `borrow_paid`).
+    ///
+    /// ### Arguments
+    /// * `annual_rate` - The annualized borrow rate (e.g., 0.05 for 5% a year).
+    ///
+    /// ### Returns
+    /// The backtest instance, or an error if the rate is negative or not finite.
+    pub fn with_borrow_fee(mut self, annual_rate: f64) -> Result<Self> {
+        if !annual_rate.is_finite() || annual_rate < 0.0 {
+            return Err(Error::InvalidRate(annual_rate));
+        }
+        self.borrow_rate = annual_rate;
+        Ok(self)
+    }
+
+    /// Rejects the orders opening or increasing a short position, as for a hard-to-borrow asset.
+    ///
+    /// ### Arguments
+    /// * `hard_to_borrow` - True if the asset can not be borrowed.
+    ///
+    /// ### Returns
+    /// The backtest instance.
+    pub fn with_hard_to_borrow(mut self, hard_to_borrow: bool) -> Self {
+        self.hard_to_borrow = hard_to_borrow;
+        self
+    }
+
+    /// Charges the funding of perpetual futures to the open positions.
+    ///
+    /// At each funding time inside a candle, the positions still open at the candle close pay or receive
+    /// `rate × quantity × close`, longs paying shorts when the rate is positive.
+    ///
+    /// ### Arguments
+    /// * `funding` - The funding rate series, or a constant rate with its interval.
+    ///
+    /// ### Returns
+    /// The backtest instance.
+    pub fn with_funding(mut self, funding: Funding) -> Self {
+        self.funding = Some(funding);
+        self
+    }
+
+    /// Sets the fee model charged on each fill, replacing the market fees given to `new`.
+    ///
+    /// The fee model is called once per fill, so a minimum or a maximum commission is applied
+    /// to each part of a partially filled order or partially closed position.
+    ///
+    /// ### Arguments
+    /// * `fee_model` - The fee model (e.g., `MakerTaker`, `TieredFees`).
+    ///
+    /// ### Returns
+    /// The backtest instance.
+    pub fn with_fee_model<F: FeeModel + 'static>(mut self, fee_model: F) -> Self {
+        self.fee_model = Some(Box::new(fee_model));
+        self
+    }
+
+    /// Returns the notional traded so far, entries and exits included.
+    pub fn traded_volume(&self) -> f64 {
+        self.volume
+    }
+
     /// Returns an iterator over the pending orders.
     pub fn orders(&self) -> Iter<'_, Order> {
         self.orders.iter()
@@ -167,26 +424,167 @@ impl Backtest {
     /// * `order` - The order to place.
     ///
     /// ### Returns
-    /// Ok if successful, or an error.
-    pub fn place_order(&mut self, order: Order) -> Result<()> {
-        self.wallet.lock(order.cost()?)?;
+    /// The id assigned to the order, or an error.
+    pub fn place_order(&mut self, mut order: Order) -> Result<u32> {
+        if self.hard_to_borrow && self.opens_short(&order) {
+            return Err(Error::HardToBorrow);
+        }
+        self.wallet.lock(self.margin(order.cost()?))?;
+        let id = self.ids.next_id();
+        order.set_id(id);
+        order.set_placed_index(self.index);
+        order.set_in_flight(self.latency.is_some());
         self.orders.push_back(order.clone());
         #[cfg(feature = "metrics")]
         {
             self.events.push(Event::from(&self.wallet));
             self.events.push(Event::AddOrder(order));
         }
-        Ok(())
+        Ok(id)
+    }
+
+    /// Places a group of linked orders.
+    ///
+    /// ### Arguments
+    /// * `group` - The group of orders to place (one-cancels-other or bracket).
+    ///
+    /// ### Returns
+    /// The ids assigned to the orders, or an error.
+    pub fn place_order_group(&mut self, group: OrderGroup) -> Result<Vec<u32>> {
+        let orders = group.into_orders(&mut self.ids)?;
+        if self.hard_to_borrow && orders.iter().any(|order| self.opens_short(order)) {
+            return Err(Error::HardToBorrow);
+        }
+        let cost = self.margin(orders.iter().map(|order| order.cost()).sum::<Result<f64>>()?);
+        let free_balance = self.wallet.free_balance()?;
+        if free_balance < cost {
+            return Err(Error::InsufficientFunds(cost, free_balance));
+        }
+        orders.into_iter().map(|order| self.place_order(order)).collect()
+    }
+
+    /// Places a reduce-only order closing a part of an open position.
+    ///
+    /// The order does not lock any funds. It is filled like any other order, only if its price
+    /// trades inside the candle range, and never closes more than the open quantity.
+    /// It is cancelled when the position is closed.
+    ///
+    /// ### Arguments
+    /// * `id` - The id of the position to reduce.
+    /// * `order` - The order to place, on the opposite side of the position.
+    ///
+    /// ### Returns
+    /// The id assigned to the order, or an error.
+    pub fn place_reduce_only_order(&mut self, id: u32, mut order: Order) -> Result<u32> {
+        let position = self
+            .positions
+            .iter()
+            .find(|p| p.id() == id)
+            .ok_or(Error::PositionNotFound)?;
+        let opposite_side = matches!(
+            (&position.side, &order.side),
+            (PositionSide::Long, OrderSide::Sell) | (PositionSide::Short, OrderSide::Buy)
+        );
+        if !opposite_side {
+            return Err(Error::MismatchedOrderType);
+        }
+        if order.quantity <= 0.0 || order.quantity > position.quantity {
+            return Err(Error::InvalidQuantity(order.quantity));
+        }
+        //? only entry types can be matched against the candle
+        order.entry_price()?;
+
+        let order_id = self.ids.next_id();
+        order.set_id(order_id);
+        order.set_position_id(id);
+        order.set_placed_index(self.index);
+        order.set_in_flight(self.latency.is_some());
+        self.orders.push_back(order.clone());
+        #[cfg(feature = "metrics")]
+        self.events.push(Event::AddOrder(order));
+        Ok(order_id)
+    }
+
+    /// Places an order closing a whole open position.
+    ///
+    /// ### Arguments
+    /// * `id` - The id of the position to close.
+    /// * `order_type` - The order type used to close (e.g., `OrderType::Market(price)` or `OrderType::Limit(price)`).
+    ///
+    /// ### Returns
+    /// The id assigned to the order, or an error.
+    pub fn place_close_order(&mut self, id: u32, order_type: OrderType) -> Result<u32> {
+        let position = self
+            .positions
+            .iter()
+            .find(|p| p.id() == id)
+            .ok_or(Error::PositionNotFound)?;
+        let side = match position.side {
+            PositionSide::Long => OrderSide::Sell,
+            PositionSide::Short => OrderSide::Buy,
+        };
+        let order = Order::from((order_type, position.quantity, side));
+        self.place_reduce_only_order(id, order)
+    }
+
+    /// Places an order adding to an open position.
+    ///
+    /// Once filled, the order is merged into the position: the quantities are combined,
+    /// the entry price is averaged and the exit rule of the position is kept.
+    ///
+    /// ### Arguments
+    /// * `id` - The id of the position to add to.
+    /// * `order` - The order to place, on the same side as the position.
+    ///
+    /// ### Returns
+    /// The id assigned to the order, or an error.
+    pub fn add_to_position(&mut self, id: u32, mut order: Order) -> Result<u32> {
+        let position = self
+            .positions
+            .iter()
+            .find(|p| p.id() == id)
+            .ok_or(Error::PositionNotFound)?;
+        let same_side = matches!(
+            (&position.side, &order.side),
+            (PositionSide::Long, OrderSide::Buy) | (PositionSide::Short, OrderSide::Sell)
+        );
+        if !same_side {
+            return Err(Error::MismatchedOrderType);
+        }
+        if let Some(max_pyramids) = self.max_pyramids {
+            let pending = self
+                .orders
+                .iter()
+                .filter(|o| o.add_to() == Some(id) && !o.is_remainder())
+                .count();
+            if position.entries() - 1 + pending >= max_pyramids {
+                return Err(Error::MaxPyramid(max_pyramids));
+            }
+        }
+        order.set_add_to(id);
+        self.place_order(order)
     }
 
     /// Deletes a pending order.
     ///
+    /// With a latency, the order can still be filled until the cancel reaches the market.
+    ///
     /// ### Arguments
     /// * `order` - Reference to the order to delete.
     ///
     /// ### Returns
     /// Ok if successful, or an error.
     pub fn delete_order(&mut self, order: &Order, force_remove: bool) -> Result<()> {
+        //? the cancel is delayed until it reaches the market
+        if force_remove && self.latency.is_some() {
+            let order = self
+                .orders
+                .iter_mut()
+                .find(|o| *o == order)
+                .ok_or(Error::OrderNotFound)?;
+            order.set_cancel_index(self.index);
+            return Ok(());
+        }
         if force_remove {
             let order_idx = self
                 .orders
@@ -195,7 +593,10 @@ impl Backtest {
                 .ok_or(Error::OrderNotFound)?;
             self.orders.remove(order_idx).ok_or(Error::RemoveOrder)?;
         }
-        self.wallet.unlock(order.cost()?)?;
+        //? child orders closing a position do not lock any funds
+        if order.position_id().is_none() {
+            self.wallet.unlock(self.margin(order.cost()?))?;
+        }
         #[cfg(feature = "metrics")]
         {
             self.events.push(Event::from(&self.wallet));
@@ -204,27 +605,187 @@ impl Backtest {
         Ok(())
     }
 
-    /// Opens a new position.
-    fn open_position(&mut self, position: Position) -> Result<()> {
-        self.wallet.sub(position.cost()?)?;
-        if let Some((market_fee, limit_fee)) = self.market_fees {
-            if position.is_market_type() {
-                self.wallet.sub_fees(position.cost()? * market_fee)?;
-            } else {
-                self.wallet.sub_fees(position.cost()? * limit_fee)?;
-            };
+    /// Amends a pending order.
+    ///
+    /// The cost difference of the order is locked or unlocked in the wallet.
+    ///
+    /// ### Arguments
+    /// * `id` - The id of the order to amend.
+    /// * `amendment` - The change to apply (entry price, quantity or exit rule).
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error.
+    pub fn amend_order(&mut self, id: u32, amendment: Amendment) -> Result<()> {
+        let order_idx = self
+            .orders
+            .iter()
+            .position(|o| o.id() == id)
+            .ok_or(Error::OrderNotFound)?;
+        let order = self.orders.get_mut(order_idx).ok_or(Error::OrderNotFound)?;
+        let mut amended_order = order.clone();
+        amended_order.amend(amendment)?;
+        //? child orders closing a position do not lock any funds
+        if order.position_id().is_none() {
+            //? only the initial margin is locked
+            let cost_diff = (amended_order.cost()? - order.cost()?) / self.leverage;
+            if cost_diff > 0.0 {
+                self.wallet.lock(cost_diff)?;
+            } else if cost_diff < 0.0 {
+                self.wallet.unlock(-cost_diff)?;
+            }
+        }
+        *order = amended_order;
+        #[cfg(feature = "metrics")]
+        {
+            let order = order.clone();
+            self.events.push(Event::from(&self.wallet));
+            self.events.push(Event::AmendOrder(order));
         }
+        Ok(())
+    }
+
+    /// Amends an open position.
+    ///
+    /// Only the exit rule of a position can be amended (e.g., move the stop-loss).
+    ///
+    /// ### Arguments
+    /// * `id` - The id of the position to amend.
+    /// * `amendment` - The change to apply.
+    ///
+    /// ### Returns
+    /// Ok if successful, or an error.
+    pub fn amend_position(&mut self, id: u32, amendment: Amendment) -> Result<()> {
+        if !matches!(amendment, Amendment::ExitRule(_)) {
+            return Err(Error::MismatchedOrderType);
+        }
+        let position = self
+            .positions
+            .iter_mut()
+            .find(|p| p.id() == id)
+            .ok_or(Error::PositionNotFound)?;
+        position.amend(amendment)?;
+        #[cfg(feature = "metrics")]
+        self.events.push(Event::AmendPosition(position.clone()));
+        Ok(())
+    }
+
+    /// Deletes an expired order and unlocks its funds.
+    fn expire_order(&mut self, order: &Order) -> Result<()> {
+        #[cfg(feature = "metrics")]
+        self.events.push(Event::ExpireOrder(order.clone()));
+        self.delete_order(order, false)
+    }
+
+    /// Cancels the pending orders matching the predicate and unlocks their funds.
+    fn cancel_orders<P>(&mut self, predicate: P) -> Result<()>
+    where
+        P: Fn(&Order) -> bool,
+    {
+        let (cancelled, orders): (VecDeque<_>, VecDeque<_>) =
+            std::mem::take(&mut self.orders).into_iter().partition(predicate);
+        self.orders = orders;
+        for order in cancelled {
+            self.delete_order(&order, false)?;
+        }
+        Ok(())
+    }
+
+    /// Opens a new position.
+    fn open_position(&mut self, mut position: Position) -> Result<u32> {
+        self.hold_collateral(&position.side, position.cost()?)?;
+        let id = self.ids.next_id();
+        position.set_id(id);
+        position.set_entry_index(self.index);
+        let order: &Order = &position;
+        self.charge_fees(
+            position.entry_price()?,
+            position.quantity,
+            &order.side,
+            position.liquidity(),
+        )?;
         self.positions.push_back(position.clone());
         #[cfg(feature = "metrics")]
         {
             self.events.push(Event::from(&self.wallet));
             self.events.push(Event::AddPosition(position));
         }
+        Ok(id)
+    }
+
+    /// Turns a filled order into a position.
+    ///
+    /// In one-way mode, the order is netted with the open position: it increases the position
+    /// on the same side, or reduces it and opens the rest on the opposite side.
+    ///
+    /// ### Returns
+    /// The id of the position holding the order quantity, if any.
+    fn fill_order(&mut self, mut order: Order) -> Result<Option<u32>> {
+        let position = match self.account_mode {
+            AccountMode::Hedge => None,
+            AccountMode::OneWay => self.positions.front().cloned(),
+        };
+        let Some(position) = position else {
+            return self.open_position(Position::from(order)).map(Some);
+        };
+
+        match (&position.side, &order.side) {
+            (PositionSide::Long, OrderSide::Buy) | (PositionSide::Short, OrderSide::Sell) => {
+                self.increase_position(position.id(), &order)?;
+                Ok(Some(position.id()))
+            }
+            _ => {
+                let price = order.entry_price()?;
+                let quantity = order.quantity.min(position.quantity);
+                //? the reducing part of the order does not open anything
+                self.wallet.unlock(self.margin(price * quantity))?;
+                self.reduce_position(&position, quantity, price, order.liquidity())?;
+                order.quantity -= quantity;
+                if order.quantity > 0.0 {
+                    self.open_position(Position::from(order)).map(Some)
+                } else if position.quantity > quantity {
+                    Ok(Some(position.id()))
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    /// Adds a filled order to an open position on the same side.
+    fn increase_position(&mut self, id: u32, order: &Order) -> Result<()> {
+        //? the position is looked up before any funds are moved
+        let pos_idx = self
+            .positions
+            .iter()
+            .position(|p| p.id() == id)
+            .ok_or(Error::PositionNotFound)?;
+        let side = match order.side {
+            OrderSide::Buy => PositionSide::Long,
+            OrderSide::Sell => PositionSide::Short,
+        };
+        self.hold_collateral(&side, order.cost()?)?;
+        self.charge_fees(order.entry_price()?, order.quantity, &order.side, order.liquidity())?;
+        let position = self.positions.get_mut(pos_idx).ok_or(Error::PositionNotFound)?;
+        //? the remaining quantity of a partially filled order is not a new entry
+        if order.is_remainder() {
+            position.merge(order.quantity, order.entry_price()?)?;
+        } else {
+            position.increase(order.quantity, order.entry_price()?)?;
+        }
+        #[cfg(feature = "metrics")]
+        {
+            let position = position.clone();
+            self.events.push(Event::from(&self.wallet));
+            self.events.push(Event::AmendPosition(position));
+        }
         Ok(())
     }
 
     /// Closes an existing position.
     ///
+    /// The exit price is not checked against the candle, use `place_close_order` to close
+    /// the position through the order queue.
+    ///
     /// ### Arguments
     /// * `position` - Reference to the position to close.
     /// * `exit_price` - The price at which to close the position.
@@ -233,29 +794,200 @@ impl Backtest {
     /// ### Returns
     /// The profit/loss from closing the position, or an error.
     pub fn close_position(&mut self, position: &Position, exit_price: f64, force_remove: bool) -> Result<f64> {
+        self.remove_position(position, exit_price, force_remove, Liquidity::Taker)
+    }
+
+    /// Closes an existing position with the given fill liquidity.
+    fn remove_position(
+        &mut self,
+        position: &Position,
+        exit_price: f64,
+        force_remove: bool,
+        liquidity: Liquidity,
+    ) -> Result<f64> {
         if exit_price <= 0.0 || !exit_price.is_finite() {
             return Err(Error::ExitPrice(exit_price));
         }
-        if force_remove {
+        //? the stored position is up to date (e.g. after a partial close)
+        let position = if force_remove {
             let pos_idx = self
                 .positions
                 .iter()
                 .position(|p| p == position)
                 .ok_or(Error::PositionNotFound)?;
-            self.positions.remove(pos_idx).ok_or(Error::RemovePosition)?;
+            self.positions.remove(pos_idx).ok_or(Error::RemovePosition)?
+        } else {
+            position.clone()
+        };
+        let pnl = self.settle_position(&position, exit_price, liquidity)?;
+        //? child orders and additions of a closed position are orphaned
+        self.cancel_orders(|o| o.position_id() == Some(position.id()) || o.add_to() == Some(position.id()))?;
+        Ok(pnl)
+    }
+
+    /// Closes a part of an existing position.
+    ///
+    /// The remaining quantity stays open with the same exit rule.
+    /// If the quantity is the whole position, it is closed as with `close_position`.
+    ///
+    /// ### Arguments
+    /// * `position` - Reference to the position to partially close.
+    /// * `quantity` - The quantity to close.
+    /// * `exit_price` - The price at which to close the quantity.
+    ///
+    /// ### Returns
+    /// The profit/loss from the closed quantity, or an error.
+    pub fn close_position_partial(&mut self, position: &Position, quantity: f64, exit_price: f64) -> Result<f64> {
+        self.reduce_position(position, quantity, exit_price, Liquidity::Taker)
+    }
+
+    /// Closes a part of an existing position with the given fill liquidity.
+    fn reduce_position(
+        &mut self,
+        position: &Position,
+        quantity: f64,
+        exit_price: f64,
+        liquidity: Liquidity,
+    ) -> Result<f64> {
+        if exit_price <= 0.0 || !exit_price.is_finite() {
+            return Err(Error::ExitPrice(exit_price));
+        }
+        let pos_idx = self
+            .positions
+            .iter()
+            .position(|p| p == position)
+            .ok_or(Error::PositionNotFound)?;
+        let open_position = self.positions.get_mut(pos_idx).ok_or(Error::PositionNotFound)?;
+        //? relative tolerance for the rounding of the quantities closed in several steps
+        let epsilon = open_position.quantity * QUANTITY_EPSILON;
+        let remaining = open_position.quantity - quantity;
+        if quantity <= 0.0 || !quantity.is_finite() || quantity > open_position.quantity + epsilon {
+            return Err(Error::InvalidQuantity(quantity));
+        }
+        if remaining.abs() <= epsilon {
+            return self.remove_position(position, exit_price, true, liquidity);
+        }
+
+        let mut closed_position = open_position.clone();
+        closed_position.quantity = quantity;
+        open_position.quantity = remaining;
+        self.settle_position(&closed_position, exit_price, liquidity)
+    }
+
+    /// Returns the stop price if the trailing stop is hit, otherwise trails it with the candle.
+    fn execute_trailing_stop(position: &mut Position, execute_price: f64, candle: &Candle) -> Option<(f64, Liquidity)> {
+        match position.side {
+            PositionSide::Long => {
+                if execute_price >= candle.low() {
+                    return Some(stop_fill(&position.side, execute_price, candle));
+                }
+                position.set_trailingstop(candle.high());
+            }
+            PositionSide::Short => {
+                if execute_price <= candle.high() {
+                    return Some(stop_fill(&position.side, execute_price, candle));
+                }
+                position.set_trailingstop(candle.low());
+            }
+        }
+        None
+    }
+
+    /// Returns the fill price moved by the slippage model, for the fills removing liquidity.
+    fn slipped_price(&self, price: f64, side: &OrderSide, quantity: f64, liquidity: Liquidity, candle: &Candle) -> f64 {
+        let Some(slippage) = self.slippage.as_ref().filter(|_| liquidity == Liquidity::Taker) else {
+            return price;
+        };
+        let slippage = slippage.slippage(price, quantity, candle).max(0.0);
+        match side {
+            OrderSide::Buy => price + slippage,
+            OrderSide::Sell => price - slippage,
+        }
+    }
+
+    /// Moves the funds of a fill opening or increasing a position.
+    ///
+    /// A long pays its initial margin, while a short is credited with the proceeds of the sale,
+    /// held as collateral with its initial margin until the position is bought back.
+    fn hold_collateral(&mut self, side: &PositionSide, cost: f64) -> Result<()> {
+        match side {
+            PositionSide::Long => {
+                self.wallet.sub(self.margin(cost))?;
+            }
+            PositionSide::Short => self.wallet.credit_short(cost, self.margin(cost)),
+        }
+        Ok(())
+    }
+
+    /// Returns true if the order opens or increases a short position once filled.
+    fn opens_short(&self, order: &Order) -> bool {
+        if order.position_id().is_some() || !matches!(order.side, OrderSide::Sell) {
+            return false;
+        }
+        match self.account_mode {
+            AccountMode::Hedge => true,
+            //? a sell only reducing the net long position does not borrow anything
+            AccountMode::OneWay => self
+                .positions
+                .front()
+                .is_none_or(|p| matches!(p.side, PositionSide::Short) || p.quantity < order.quantity),
+        }
+    }
+
+    /// Returns the initial margin of a notional, the whole notional without leverage.
+    fn margin(&self, notional: f64) -> f64 {
+        notional / self.leverage
+    }
+
+    /// Returns the fee of a fill at the current traded volume, negative for a rebate.
+    fn fee(&self, price: f64, quantity: f64, side: &OrderSide, liquidity: Liquidity) -> f64 {
+        self.fee_model.as_ref().map_or(0.0, |fee_model| {
+            fee_model.fee(price, quantity, side, liquidity, self.volume)
+        })
+    }
+
+    /// Charges the fee of a fill and adds its notional to the traded volume.
+    fn charge_fees(&mut self, price: f64, quantity: f64, side: &OrderSide, liquidity: Liquidity) -> Result<()> {
+        let fee = self.fee(price, quantity, side, liquidity);
+        self.volume += price * quantity;
+        if fee != 0.0 {
+            self.wallet.sub_fees(fee)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the price at which the position is closed without loss, fees included.
+    fn break_even_price(&self, position: &Position) -> Result<f64> {
+        let entry_price = position.entry_price()?;
+        //? fees are charged on both opening and closing, the stop exit removes liquidity
+        let (order, quantity): (&Order, f64) = (position, position.quantity);
+        let fees = (self.fee(entry_price, quantity, &order.side, position.liquidity())
+            + self.fee(entry_price, quantity, &position.side.exit_side(), Liquidity::Taker))
+            / position.cost()?;
+        match position.side {
+            PositionSide::Long => Ok(entry_price * (1.0 + fees)),
+            PositionSide::Short => Ok(entry_price * (1.0 - fees)),
         }
+    }
+
+    /// Realizes the profit/loss of a removed position and charges the fees.
+    fn settle_position(&mut self, position: &Position, exit_price: f64, liquidity: Liquidity) -> Result<f64> {
         // Calculate profit/loss and update wallet
         let pnl = position.estimate_pnl(exit_price)?;
-        let total_amount = pnl + position.cost()?;
-        self.wallet.add(total_amount)?;
-        self.wallet.sub_pnl(total_amount);
-        if let Some((market_fee, limit_fee)) = self.market_fees {
-            if position.is_market_type() {
-                self.wallet.sub_fees(position.cost()? * market_fee)?;
-            } else {
-                self.wallet.sub_fees(position.cost()? * limit_fee)?;
-            };
+        let (cost, margin) = (position.cost()?, self.margin(position.cost()?));
+        let total_amount = pnl + margin;
+        match position.side {
+            PositionSide::Long => {
+                self.wallet.add(total_amount)?;
+            }
+            PositionSide::Short => {
+                //? the collateral is released and the proceeds pay the buy back
+                self.wallet.release_short(cost, margin)?;
+                self.wallet.add(pnl - cost)?;
+            }
         }
+        self.wallet.sub_pnl(total_amount);
+        self.charge_fees(exit_price, position.quantity, &position.side.exit_side(), liquidity)?;
         #[cfg(feature = "metrics")]
         {
             let mut position = position.clone();
@@ -280,23 +1012,287 @@ impl Backtest {
         Ok(())
     }
 
+    /// Returns true if an order or a cancel sent on the given candle has reached the market.
+    fn latency_elapsed(&self, sent_index: usize, candle: &Candle) -> bool {
+        match &self.latency {
+            None => true,
+            Some(Latency::Bars(bars)) => sent_index.checked_add(*bars).is_some_and(|index| self.index >= index),
+            Some(Latency::Duration(duration)) => {
+                let duration = chrono::TimeDelta::from_std(*duration).unwrap_or(chrono::TimeDelta::MAX);
+                //? a latency beyond the representable dates is never elapsed
+                self.data.get(sent_index).is_none_or(|sent_candle| {
+                    sent_candle
+                        .close_time()
+                        .checked_add_signed(duration)
+                        .is_some_and(|arrival_time| arrival_time <= candle.close_time())
+                })
+            }
+        }
+    }
+
+    /// Moves an order to its fill price, adjusting the locked funds.
+    ///
+    /// ### Returns
+    /// False if the free balance does not cover the new cost of the order.
+    fn reprice_order(&mut self, order: &mut Order, price: f64) -> Result<bool> {
+        let mut repriced_order = order.clone();
+        repriced_order.set_entry_price(price);
+        //? child orders closing a position do not lock any funds
+        if order.position_id().is_none() {
+            let cost_diff = self.margin(repriced_order.cost()? - order.cost()?);
+            if cost_diff > 0.0 {
+                if self.wallet.free_balance()? < cost_diff {
+                    return Ok(false);
+                }
+                self.wallet.lock(cost_diff)?;
+            } else if cost_diff < 0.0 {
+                self.wallet.unlock(-cost_diff)?;
+            }
+        }
+        *order = repriced_order;
+        Ok(true)
+    }
+
+    /// Puts the stop-loss leg of a bracket before its take-profit leg when the intrabar path reaches it first.
+    fn order_bracket_legs(&mut self, candle: &Candle) {
+        for tp_idx in 0..self.orders.len() {
+            let leg = &self.orders[tp_idx];
+            let (Some(group), Some(_), OrderType::Limit(take_profit)) =
+                (leg.group(), leg.position_id(), leg.entry_type())
+            else {
+                continue;
+            };
+            let Some(sl_idx) = self
+                .orders
+                .iter()
+                .position(|o| o.group() == Some(group) && matches!(o.entry_type(), OrderType::Stop(_)))
+                .filter(|sl_idx| *sl_idx > tp_idx)
+            else {
+                continue;
+            };
+            let OrderType::Stop(stop_loss) = self.orders[sl_idx].entry_type() else {
+                continue;
+            };
+            let side = match leg.side {
+                OrderSide::Sell => PositionSide::Long,
+                OrderSide::Buy => PositionSide::Short,
+            };
+            if exits_reached(&side, *take_profit, *stop_loss, candle) == (true, true)
+                && !self
+                    .intrabar_path
+                    .take_profit_first(&side, *take_profit, *stop_loss, candle)
+            {
+                self.orders.swap(tp_idx, sl_idx);
+            }
+        }
+    }
+
     /// Executes pending orders based on current candle data.
     fn execute_orders(&mut self, candle: &Candle) -> Result<()> {
+        self.order_bracket_legs(candle);
         let mut orders = VecDeque::with_capacity(self.orders.len());
-        while let Some(order) = self.orders.pop_front() {
+        let mut filled_groups = Vec::new();
+        while let Some(mut order) = self.orders.pop_front() {
+            //? a sibling has already been filled, the order will be cancelled
+            if order.group().is_some_and(|group| filled_groups.contains(&group)) {
+                orders.push_back(order);
+                continue;
+            }
+
+            if order
+                .cancel_index()
+                .is_some_and(|index| self.latency_elapsed(index, candle))
+            {
+                self.delete_order(&order, false)?;
+                continue;
+            }
+
+            if order.is_expired(candle.close_time()) {
+                self.expire_order(&order)?;
+                continue;
+            }
+
+            if order.in_flight() {
+                if !self.latency_elapsed(order.placed_index(), candle) {
+                    orders.push_back(order);
+                    continue;
+                }
+                order.set_in_flight(false);
+            }
+
+            let placed_now = order.placed_index() == self.index;
+            let fill_price = match self.execution_model {
+                //? the remaining quantity of a market or triggered stop order is filled at the next open
+                _ if !placed_now && order.is_remainder() && (order.is_market_type() || order.is_stop_type()) => {
+                    Some(candle.open())
+                }
+                ExecutionModel::CurrentBar => None,
+                ExecutionModel::SameClose if placed_now && order.is_market_type() => Some(candle.close()),
+                ExecutionModel::NextOpen if !placed_now && order.is_market_type() => Some(candle.open()),
+                ExecutionModel::SameClose | ExecutionModel::NextOpen if placed_now => {
+                    //? the candle has already been traded when the order is placed
+                    orders.push_back(order);
+                    continue;
+                }
+                _ => None,
+            };
+            if let Some(fill_price) = fill_price
+                && !self.reprice_order(&mut order, fill_price)?
+            {
+                self.expire_order(&order)?;
+                continue;
+            }
+
+            let mut trigger_price = None;
+            if let (OrderState::Armed, Some(stop_price)) = (order.state(), order.stop_price()) {
+                //? a stop order is triggered when the candle trades through its stop price
+                let triggered = match order.side {
+                    OrderSide::Buy => stop_price <= candle.high(),
+                    OrderSide::Sell => stop_price >= candle.low(),
+                };
+                if !triggered {
+                    if order.tick() {
+                        self.expire_order(&order)?;
+                    } else {
+                        orders.push_back(order);
+                    }
+                    continue;
+                }
+                order.trigger();
+                #[cfg(feature = "metrics")]
+                self.events.push(Event::TriggerOrder(order.clone()));
+                //? a stop-limit triggered inside the candle trades from its stop price, or the open beyond it
+                if matches!(order.entry_type(), OrderType::StopLimit(..)) {
+                    trigger_price = Some(match order.side {
+                        OrderSide::Buy => stop_price.max(candle.open()),
+                        OrderSide::Sell => stop_price.min(candle.open()),
+                    });
+                }
+            }
+
             let price = order.entry_price()?;
-            if price >= candle.low() && price <= candle.high() {
-                self.open_position(Position::from(order))?;
-            } else {
-                //? if order is market type and does not between `high` and `low`, delete
-                if order.is_market_type() {
-                    self.delete_order(&order, false)?;
+            let gap_price = match trigger_price {
+                //? filled at the trigger price when it is at the limit or better
+                Some(trigger_price) => match order.side {
+                    OrderSide::Buy => (trigger_price <= price).then_some(trigger_price),
+                    OrderSide::Sell => (trigger_price >= price).then_some(trigger_price),
+                },
+                None => order.gap_price(candle.open()),
+            };
+            let reached = gap_price.is_some()
+                || order.is_stop_type()
+                || match (order.entry_type(), &order.side) {
+                    (OrderType::Market(_), _) => price >= candle.low() && price <= candle.high(),
+                    //? limit orders (and triggered stop-limits) are filled at the limit or better
+                    (_, OrderSide::Buy) => price >= candle.low(),
+                    (_, OrderSide::Sell) => price <= candle.high(),
+                };
+            //? the participation rate limits the filled quantity to a part of the candle volume
+            let max_quantity = self
+                .participation_rate
+                .map_or(f64::INFINITY, |rate| rate * candle.volume());
+            let killed = order.quantity > max_quantity && *order.time_in_force() == TimeInForce::FillOrKill;
+            if !reached || max_quantity <= 0.0 || killed {
+                //? e.g. a market order which does not between `high` and `low`
+                if order.tick() {
+                    self.expire_order(&order)?;
                 } else {
                     orders.push_back(order);
                 }
+                continue;
+            }
+
+            let mut remaining = None;
+            if order.quantity > max_quantity {
+                let mut remaining_order = order.clone();
+                remaining_order.quantity -= max_quantity;
+                remaining_order.set_group(None);
+                remaining_order.take_legs();
+                remaining_order.set_remainder();
+                if remaining_order.is_market_type() && !remaining_order.has_time_in_force() {
+                    remaining_order.set_time_in_force(TimeInForce::GoodTillCancelled);
+                }
+                order.quantity = max_quantity;
+                remaining = Some(remaining_order);
+            }
+
+            let fill_price = gap_price.unwrap_or(price);
+            let slipped_price = self.slipped_price(fill_price, &order.side, order.quantity, order.liquidity(), candle);
+            if slipped_price != price && !self.reprice_order(&mut order, slipped_price)? {
+                self.expire_order(&order)?;
+                if let Some(remaining_order) = remaining {
+                    self.expire_order(&remaining_order)?;
+                }
+                continue;
+            }
+            self.wallet
+                .add_slippage((slipped_price - fill_price).abs() * order.quantity);
+            let price = slipped_price;
+
+            if let Some(group) = order.group() {
+                filled_groups.push(group);
+            }
+            #[cfg(feature = "metrics")]
+            if remaining.is_some() {
+                self.events.push(Event::PartialFillOrder(order.clone()));
+            }
+            let position_id = match (order.position_id(), order.add_to()) {
+                (Some(position_id), _) => {
+                    let position = self.positions.iter().find(|p| p.id() == position_id).cloned();
+                    if let Some(position) = position {
+                        //? reduce-only: never closes more than the open quantity
+                        let quantity = order.quantity.min(position.quantity);
+                        self.reduce_position(&position, quantity, price, order.liquidity())?;
+                    }
+                    Some(position_id)
+                }
+                (None, Some(position_id)) => {
+                    self.increase_position(position_id, &order)?;
+                    Some(position_id)
+                }
+                (None, None) => {
+                    let legs = order.take_legs();
+                    //? the order may only reduce the net position, leaving nothing to protect
+                    let position_id = self.fill_order(order)?;
+                    if let Some(position_id) = position_id {
+                        for mut leg in legs {
+                            leg.set_id(self.ids.next_id());
+                            leg.set_position_id(position_id);
+                            leg.set_placed_index(self.index);
+                            #[cfg(feature = "metrics")]
+                            self.events.push(Event::AddOrder(leg.clone()));
+                            orders.push_back(leg);
+                        }
+                    }
+                    position_id
+                }
+            };
+
+            if let Some(mut remaining_order) = remaining {
+                //? the remaining quantity adds to the position opened by the filled part (netted in one-way mode)
+                if let (AccountMode::Hedge, None, Some(position_id)) =
+                    (&self.account_mode, remaining_order.position_id(), position_id)
+                {
+                    remaining_order.set_add_to(position_id);
+                }
+                if remaining_order.tick() {
+                    self.expire_order(&remaining_order)?;
+                } else {
+                    orders.push_back(remaining_order);
+                }
             }
         }
         self.orders.append(&mut orders);
+        for group in filled_groups {
+            self.cancel_orders(|o| o.group() == Some(group))?;
+        }
+        //? orders already matched on this candle are missed when their position is closed
+        let position_ids = self.positions.iter().map(|p| p.id()).collect::<Vec<_>>();
+        self.cancel_orders(|o| {
+            o.position_id()
+                .or(o.add_to())
+                .is_some_and(|id| !position_ids.contains(&id))
+        })?;
         Ok(())
     }
 
@@ -305,77 +1301,160 @@ impl Backtest {
         let mut positions = VecDeque::with_capacity(self.positions.len());
 
         while let Some(mut position) = self.positions.pop_front() {
+            //? a position opened at the candle close can not be exited inside the same candle
+            if self.execution_model == ExecutionModel::SameClose && position.entry_index() == self.index {
+                positions.push_back(position);
+                continue;
+            }
+
             let should_close = match position.exit_rule() {
                 Some(OrderType::TakeProfitAndStopLoss(take_profit, stop_loss)) => {
                     if *take_profit < 0.0 || *stop_loss < 0.0 {
                         return Err(Error::NegTakeProfitAndStopLoss);
                     }
 
-                    match position.side {
-                        PositionSide::Long => {
-                            if *take_profit > 0.0 && take_profit <= &candle.high() {
-                                Some(*take_profit)
-                            } else if *stop_loss > 0.0 && stop_loss >= &candle.low() {
-                                Some(*stop_loss)
+                    match exits_reached(&position.side, *take_profit, *stop_loss, candle) {
+                        (true, true) => {
+                            if self
+                                .intrabar_path
+                                .take_profit_first(&position.side, *take_profit, *stop_loss, candle)
+                            {
+                                Some(take_profit_fill(&position.side, *take_profit, candle))
                             } else {
-                                None
+                                Some(stop_fill(&position.side, *stop_loss, candle))
                             }
                         }
-                        PositionSide::Short => {
-                            if *take_profit > 0.0 && take_profit >= &candle.low() {
-                                Some(*take_profit)
-                            } else if *stop_loss > 0.0 && stop_loss <= &candle.high() {
-                                Some(*stop_loss)
-                            } else {
-                                None
-                            }
+                        (true, false) => Some(take_profit_fill(&position.side, *take_profit, candle)),
+                        (false, true) => Some(stop_fill(&position.side, *stop_loss, candle)),
+                        (false, false) => None,
+                    }
+                }
+                Some(OrderType::TakeProfitLadder(levels, stop_loss)) => {
+                    let levels = VecDeque::from(levels.clone());
+                    let stop_loss = *stop_loss;
+                    self.execute_ladder(&mut position, levels, stop_loss, candle)?
+                }
+                Some(OrderType::BreakEvenStop(stop_loss, trigger)) => {
+                    if *stop_loss < 0.0 || *trigger < 0.0 {
+                        return Err(Error::NegTakeProfitAndStopLoss);
+                    }
+
+                    let (stop_loss, trigger) = (*stop_loss, *trigger);
+                    let (stopped, triggered) = match position.side {
+                        PositionSide::Long => (stop_loss >= candle.low(), trigger > 0.0 && trigger <= candle.high()),
+                        PositionSide::Short => (stop_loss <= candle.high(), trigger > 0.0 && trigger >= candle.low()),
+                    };
+                    if stop_loss > 0.0 && stopped {
+                        Some(stop_fill(&position.side, stop_loss, candle))
+                    } else {
+                        if triggered {
+                            let stop_loss = self.break_even_price(&position)?;
+                            let exit_rule = OrderType::BreakEvenStop(stop_loss, 0.0);
+                            position.amend(Amendment::ExitRule(Some(exit_rule)))?;
                         }
+                        None
                     }
                 }
-                Some(OrderType::TrailingStop(price, percent)) => {
-                    if *price <= 0.0 || *percent <= 0.0 {
+                Some(OrderType::SteppedStop(stop_loss, trigger, step)) => {
+                    if *stop_loss <= 0.0 || *trigger <= 0.0 || *step <= 0.0 {
                         return Err(Error::NegZeroTrailingStop);
                     }
 
+                    let (mut stop_loss, mut trigger, step) = (*stop_loss, *trigger, *step);
                     match position.side {
                         PositionSide::Long => {
-                            let execute_price = price.subpercent(*percent);
-                            if execute_price >= candle.low() {
-                                Some(execute_price)
+                            if stop_loss >= candle.low() {
+                                Some(stop_fill(&position.side, stop_loss, candle))
                             } else {
-                                if &candle.high() > price {
-                                    position.set_trailingstop(candle.high());
+                                //? the number of steps is computed at once, a tiny step would loop for long
+                                if trigger <= candle.high() {
+                                    let steps = ((candle.high() - trigger) / step).floor() + 1.0;
+                                    stop_loss += steps * step;
+                                    trigger += steps * step;
                                 }
+                                let exit_rule = OrderType::SteppedStop(stop_loss, trigger, step);
+                                position.amend(Amendment::ExitRule(Some(exit_rule)))?;
                                 None
                             }
                         }
                         PositionSide::Short => {
-                            let execute_price = price.addpercent(*percent);
-                            if execute_price <= candle.high() {
-                                Some(execute_price)
+                            if stop_loss <= candle.high() {
+                                Some(stop_fill(&position.side, stop_loss, candle))
                             } else {
-                                if &candle.low() < price {
-                                    position.set_trailingstop(candle.low());
+                                if trigger >= candle.low() {
+                                    let steps = ((trigger - candle.low()) / step).floor() + 1.0;
+                                    stop_loss -= steps * step;
+                                    trigger -= steps * step;
                                 }
+                                let exit_rule = OrderType::SteppedStop(stop_loss, trigger, step);
+                                position.amend(Amendment::ExitRule(Some(exit_rule)))?;
                                 None
                             }
                         }
                     }
                 }
+                Some(OrderType::TrailingStop(price, percent)) => {
+                    if *price <= 0.0 || *percent <= 0.0 {
+                        return Err(Error::NegZeroTrailingStop);
+                    }
+
+                    let execute_price = match position.side {
+                        PositionSide::Long => price.subpercent(*percent),
+                        PositionSide::Short => price.addpercent(*percent),
+                    };
+                    Self::execute_trailing_stop(&mut position, execute_price, candle)
+                }
+                Some(OrderType::TrailingStopActivation(price, percent, activation)) => {
+                    if *price <= 0.0 || *percent <= 0.0 || *activation < 0.0 {
+                        return Err(Error::NegZeroTrailingStop);
+                    }
+
+                    let execute_price = match position.side {
+                        PositionSide::Long => price.subpercent(*percent),
+                        PositionSide::Short => price.addpercent(*percent),
+                    };
+                    Self::execute_trailing_stop(&mut position, execute_price, candle)
+                }
+                Some(OrderType::TrailingStopDistance(price, distance, activation)) => {
+                    if *price <= 0.0 || *distance <= 0.0 || *activation < 0.0 {
+                        return Err(Error::NegZeroTrailingStop);
+                    }
+
+                    let execute_price = match position.side {
+                        PositionSide::Long => price - distance,
+                        PositionSide::Short => price + distance,
+                    };
+                    Self::execute_trailing_stop(&mut position, execute_price, candle)
+                }
                 None => None,
                 _ => {
                     return Err(Error::MismatchedOrderType);
                 }
             };
 
+            //? the time stop closes at the candle close, after the price exit rule had its chance
+            let should_close = should_close.or_else(|| {
+                position
+                    .is_time_stopped(self.index, candle.close_time())
+                    .then(|| (candle.close(), Liquidity::Taker))
+            });
+
             match should_close {
-                Some(exit_price) => {
-                    self.close_position(&position, exit_price, false)?;
+                Some((exit_price, liquidity)) => {
+                    //? the position is closed by selling a long or buying back a short
+                    let side = position.side.exit_side();
+                    let slipped_price = self.slipped_price(exit_price, &side, position.quantity, liquidity, candle);
+                    self.wallet
+                        .add_slippage((slipped_price - exit_price).abs() * position.quantity);
+                    self.remove_position(&position, slipped_price, false, liquidity)?;
                 }
                 None => positions.push_back(position),
             }
         }
 
+        self.pay_funding(&positions, candle)?;
+        self.pay_borrow_fee(&positions, candle)?;
+
         let mut total_unrealized_pnl = 0.0;
         for position in &positions {
             // calculate unrealized P&L for this position
@@ -384,12 +1463,235 @@ impl Backtest {
             total_unrealized_pnl += pnl;
         }
 
+        if let Some((maintenance_margin, liquidation_fee)) = self.margin {
+            //? the equity is checked at the worst price reached by each position, not only at the close
+            let mut equity = self.wallet.balance();
+            let mut notional = 0.0;
+            for position in &positions {
+                let price = adverse_price(&position.side, candle);
+                equity += self.posted_margin(position)? + position.estimate_pnl(price)?;
+                notional += position.quantity * price;
+            }
+            if !positions.is_empty() && equity < notional * maintenance_margin {
+                self.liquidate(&mut positions, maintenance_margin, liquidation_fee, candle)?;
+                total_unrealized_pnl = 0.0;
+            }
+        }
+
         self.positions.append(&mut positions);
         self.wallet.set_unrealized_pnl(total_unrealized_pnl);
         //? new event wallet
         Ok(())
     }
 
+    /// Pays the funding of the funding times since the previous candle.
+    fn pay_funding(&mut self, positions: &VecDeque<Position>, candle: &Candle) -> Result<()> {
+        let Some(funding) = &self.funding else {
+            return Ok(());
+        };
+        let start = self.funding_time.unwrap_or(candle.open_time());
+        let rate = funding.rates(start, candle.close_time()).iter().sum::<f64>();
+        self.funding_time = Some(candle.close_time());
+        if rate == 0.0 || positions.is_empty() {
+            return Ok(());
+        }
+
+        let amount = positions
+            .iter()
+            .map(|position| match position.side {
+                PositionSide::Long => rate * position.quantity * candle.close(),
+                PositionSide::Short => -rate * position.quantity * candle.close(),
+            })
+            .sum::<f64>();
+        self.wallet.sub_funding(amount)?;
+        #[cfg(feature = "metrics")]
+        self.events.push(Event::from(&self.wallet));
+        Ok(())
+    }
+
+    /// Accrues the borrow fee of the short positions over the candle.
+    fn pay_borrow_fee(&mut self, positions: &VecDeque<Position>, candle: &Candle) -> Result<()> {
+        if self.borrow_rate == 0.0 {
+            return Ok(());
+        }
+        const YEAR_SECONDS: f64 = 365.0 * 24.0 * 3600.0;
+        let duration = (candle.close_time() - candle.open_time()).as_seconds_f64();
+        let notional = positions
+            .iter()
+            .filter(|p| matches!(p.side, PositionSide::Short))
+            .map(|p| p.quantity * candle.close())
+            .sum::<f64>();
+        let fee = self.borrow_rate * notional * duration / YEAR_SECONDS;
+        if fee > 0.0 {
+            self.wallet.sub_borrow(fee)?;
+            #[cfg(feature = "metrics")]
+            self.events.push(Event::from(&self.wallet));
+        }
+        Ok(())
+    }
+
+    /// Returns the margin posted by a position and held in the balance.
+    ///
+    /// The balance of a short includes its proceeds, which are owed back.
+    fn posted_margin(&self, position: &Position) -> Result<f64> {
+        Ok(match position.side {
+            PositionSide::Long => self.margin(position.cost()?),
+            PositionSide::Short => -position.cost()?,
+        })
+    }
+
+    /// Returns the price at which the equity reaches the maintenance margin, inside the candle.
+    ///
+    /// The other positions are held at their worst price of the candle, and the position is filled
+    /// at the open when the candle opens beyond its liquidation price.
+    fn liquidation_price(
+        &self,
+        positions: &VecDeque<Position>,
+        position: &Position,
+        maintenance_margin: f64,
+        candle: &Candle,
+    ) -> Result<f64> {
+        let mut equity = self.wallet.balance();
+        let mut notional = 0.0;
+        for other in positions {
+            equity += self.posted_margin(other)?;
+            if other != position {
+                let price = adverse_price(&other.side, candle);
+                equity += other.estimate_pnl(price)?;
+                notional += other.quantity * price;
+            }
+        }
+        let (quantity, entry_price) = (position.quantity, position.entry_price()?);
+        let price = match position.side {
+            PositionSide::Long => {
+                let price = (maintenance_margin * notional - equity + quantity * entry_price)
+                    / (quantity * (1.0 - maintenance_margin));
+                price.min(candle.open())
+            }
+            PositionSide::Short => {
+                let price = (equity + quantity * entry_price - maintenance_margin * notional)
+                    / (quantity * (1.0 + maintenance_margin));
+                price.max(candle.open())
+            }
+        };
+        Ok(price.clamp(candle.low(), candle.high()))
+    }
+
+    /// Cancels the pending orders and closes the positions at their liquidation price, charging the liquidation fee.
+    ///
+    /// The loss not covered by the margins and the free balance is written off as a shortfall,
+    /// and the fees can not take more than what is left of the free balance.
+    fn liquidate(
+        &mut self,
+        positions: &mut VecDeque<Position>,
+        maintenance_margin: f64,
+        liquidation_fee: f64,
+        candle: &Candle,
+    ) -> Result<()> {
+        self.cancel_orders(|_| true)?;
+        let exit_prices = positions
+            .iter()
+            .map(|position| self.liquidation_price(positions, position, maintenance_margin, candle))
+            .collect::<Result<VecDeque<_>>>()?;
+        let mut amount = 0.0;
+        for (position, exit_price) in positions.iter().zip(&exit_prices) {
+            #[cfg(feature = "metrics")]
+            self.events.push(Event::Liquidation(position.clone()));
+            let exit_price = *exit_price;
+            let pnl = position.estimate_pnl(exit_price)?;
+            let (cost, margin) = (position.cost()?, self.margin(position.cost()?));
+            amount += match position.side {
+                PositionSide::Long => pnl + margin,
+                PositionSide::Short => {
+                    self.wallet.release_short(cost, margin)?;
+                    pnl - cost
+                }
+            };
+            self.wallet.sub_pnl(pnl + margin);
+        }
+        //? the profit of a position covers the loss of the others before the shortfall is written off
+        self.wallet.add_liquidation(amount);
+
+        for (position, exit_price) in positions.drain(..).zip(exit_prices) {
+            let quantity = position.quantity;
+            let fee = self.fee(exit_price, quantity, &position.side.exit_side(), Liquidity::Taker)
+                + quantity * exit_price * liquidation_fee;
+            let fee = fee.min(self.wallet.free_balance()?);
+            self.volume += exit_price * quantity;
+            if fee != 0.0 {
+                self.wallet.sub_fees(fee)?;
+            }
+            #[cfg(feature = "metrics")]
+            {
+                let mut position = position;
+                position.set_exit_price(exit_price)?;
+                self.events.push(Event::DelPosition(position));
+            }
+        }
+        #[cfg(feature = "metrics")]
+        self.events.push(Event::from(&self.wallet));
+        Ok(())
+    }
+
+    /// Closes the reached levels of a take-profit ladder, then checks the stop-loss.
+    ///
+    /// ### Returns
+    /// The exit fill if the remaining position must be closed.
+    fn execute_ladder(
+        &mut self,
+        position: &mut Position,
+        mut levels: VecDeque<(f64, f64)>,
+        stop_loss: f64,
+        candle: &Candle,
+    ) -> Result<Option<(f64, Liquidity)>> {
+        if stop_loss < 0.0 || levels.iter().any(|(take_profit, _)| *take_profit <= 0.0) {
+            return Err(Error::NegTakeProfitAndStopLoss);
+        }
+        if let Some((_, fraction)) = levels.iter().find(|(_, fraction)| *fraction <= 0.0 || *fraction > 1.0) {
+            return Err(Error::InvalidFraction(*fraction));
+        }
+
+        if let Some(&(take_profit, _)) = levels.front()
+            && exits_reached(&position.side, take_profit, stop_loss, candle) == (true, true)
+            && !self
+                .intrabar_path
+                .take_profit_first(&position.side, take_profit, stop_loss, candle)
+        {
+            return Ok(Some(stop_fill(&position.side, stop_loss, candle)));
+        }
+
+        while let Some(&(take_profit, fraction)) = levels.front() {
+            let reached = match position.side {
+                PositionSide::Long => take_profit <= candle.high(),
+                PositionSide::Short => take_profit >= candle.low(),
+            };
+            if !reached {
+                break;
+            }
+            //? a level of 1 closes the whole remaining position (rescaled fractions may not be exact)
+            if fraction >= 1.0 - 1e-9 {
+                return Ok(Some(take_profit_fill(&position.side, take_profit, candle)));
+            }
+
+            levels.pop_front();
+            let mut closed_position = position.clone();
+            closed_position.quantity = position.quantity * fraction;
+            position.quantity -= closed_position.quantity;
+            let (exit_price, liquidity) = take_profit_fill(&position.side, take_profit, candle);
+            self.settle_position(&closed_position, exit_price, liquidity)?;
+            for (_, next_fraction) in levels.iter_mut() {
+                *next_fraction = (*next_fraction / (1.0 - fraction)).min(1.0);
+            }
+        }
+        position.amend(Amendment::ExitRule(Some(OrderType::TakeProfitLadder(
+            levels.into(),
+            stop_loss,
+        ))))?;
+
+        let (_, stopped) = exits_reached(&position.side, 0.0, stop_loss, candle);
+        Ok(stopped.then(|| stop_fill(&position.side, stop_loss, candle)))
+    }
+
     /// Runs the backtest, executing the provided function for each candle.
     ///
     /// ### Arguments
@@ -418,7 +1720,7 @@ impl Backtest {
     /// ### Arguments
     /// * `aggregator` - An aggregator that defines how to group candles (e.g., by timeframe).
     /// * `func` - A closure that takes the backtest and a vector of candle references.
-    ///            The vector contains the current candle followed by any aggregated candles.
+    ///   The vector contains the current candle followed by any aggregated candles.
     ///
     /// ### Returns
     /// Ok if successful, or an error.
@@ -473,7 +1775,10 @@ impl Backtest {
     /// Resets the backtest to its initial state.
     pub fn reset(&mut self) {
         self.index = 0;
+        self.ids.reset();
         self.wallet.reset();
+        self.volume = 0.0;
+        self.funding_time = None;
         #[cfg(feature = "metrics")]
         {
             self.events = Vec::new();
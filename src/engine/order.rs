@@ -1,4 +1,6 @@
-use crate::{errors::*, utils::random_id};
+use crate::{errors::*, utils::IdGenerator};
+
+use chrono::{DateTime, Utc};
 
 /// Represents the side of an order (buy or sell).
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -10,12 +12,22 @@ pub enum OrderSide {
     Sell,
 }
 
+impl OrderSide {
+    /// Returns the opposite side.
+    pub(crate) fn opposite(&self) -> Self {
+        match self {
+            Self::Buy => Self::Sell,
+            Self::Sell => Self::Buy,
+        }
+    }
+}
+
 /// Represents the type of an order (market, limit, take-profit/stop-loss, trailing stop).
 /// Enum representing the type of an order.
 ///
 /// This enum is divided into two categories:
-/// 1. **Order types for opening positions** (Market, Limit)
-/// 2. **Exit rules for closing positions** (TakeProfit, StopLoss, TrailingStop)
+/// 1. **Order types for opening positions** (Market, Limit, Stop, StopLimit)
+/// 2. **Exit rules for closing positions** (TakeProfit, StopLoss, TakeProfitLadder, BreakEvenStop, SteppedStop, TrailingStop, TrailingStopActivation, TrailingStopDistance)
 ///
 /// This separation ensures clarity between order types used to open positions
 /// and rules used to automatically close them.
@@ -34,6 +46,25 @@ pub enum OrderType {
     /// * `0` - The limit price for the order.
     Limit(f64),
 
+    /// Stop order to open a position once the market trades through a trigger price.
+    ///
+    /// A buy stop is triggered when the candle high reaches the price,
+    /// a sell stop when the candle low reaches it. Once triggered, it is filled as a market order.
+    ///
+    /// ### Arguments
+    /// * `0` - The stop (trigger) price for the order.
+    Stop(f64),
+
+    /// Stop-limit order to open a position with a limit order once a trigger price is reached.
+    ///
+    /// Until the candle trades through the stop price, the order is armed and cannot be filled.
+    /// Once triggered, it rests as a limit order at the limit price and may stay unfilled.
+    ///
+    /// ### Arguments
+    /// * `0` - The stop (trigger) price for the order.
+    /// * `1` - The limit price for the order once triggered.
+    StopLimit(f64, f64),
+
     /// Combined take-profit and stop-loss exit rule for a position.
     ///
     /// When either the take-profit or stop-loss price is reached, the position will be closed.
@@ -43,6 +74,39 @@ pub enum OrderType {
     /// * `1` - The stop-loss price (0.0 to disable)
     TakeProfitAndStopLoss(f64, f64),
 
+    /// Take-profit ladder exit rule for a position.
+    ///
+    /// Each take-profit level closes a fraction of the position, in order.
+    /// Once a level is reached, it is removed and the fractions of the next levels are rescaled
+    /// to the remaining quantity. The stop-loss applies to the remaining quantity.
+    ///
+    /// ### Arguments
+    /// * `0` - The take-profit levels: price and fraction of the position to close (e.g., 0.5 for 50%)
+    /// * `1` - The stop-loss price (0.0 to disable)
+    TakeProfitLadder(Vec<(f64, f64)>, f64),
+
+    /// Break-even stop exit rule for a position.
+    ///
+    /// Once the trigger price is reached, the stop-loss is moved to the entry price plus the fees,
+    /// so the position can no longer be closed at a loss.
+    /// Use `PercentCalculus::addpercent` on the entry price to trigger at +N%.
+    ///
+    /// ### Arguments
+    /// * `0` - The stop-loss price (0.0 to disable until triggered)
+    /// * `1` - The trigger price (0.0 once triggered)
+    BreakEvenStop(f64, f64),
+
+    /// Stepped stop exit rule for a position.
+    ///
+    /// Each time the price reaches the trigger price, the stop-loss and the trigger price
+    /// both move by the step in favor of the position.
+    ///
+    /// ### Arguments
+    /// * `0` - The stop-loss price
+    /// * `1` - The trigger price of the next step
+    /// * `2` - The step (e.g., 5.0 to move the stop-loss by 5.0)
+    SteppedStop(f64, f64, f64),
+
     /// Trailing stop exit rule for a position.
     ///
     /// The stop price trails the market price by a specified percentage.
@@ -53,16 +117,171 @@ pub enum OrderType {
     /// * `0` - The initial stop price
     /// * `1` - The trailing percentage (e.g., 10.0 for 10%)
     TrailingStop(f64, f64),
+
+    /// Trailing stop exit rule with an activation price.
+    ///
+    /// The stop price does not trail until the market reaches the activation price
+    /// (e.g., activate at +3%, then trail by 1%).
+    ///
+    /// ### Arguments
+    /// * `0` - The initial stop price
+    /// * `1` - The trailing percentage (e.g., 1.0 for 1%)
+    /// * `2` - The activation price (0.0 once activated, or to trail from entry)
+    TrailingStopActivation(f64, f64, f64),
+
+    /// Trailing stop exit rule by an absolute distance.
+    ///
+    /// The stop price trails the market price by a fixed distance (e.g., 2×ATR supplied by the strategy),
+    /// optionally once the market reaches the activation price.
+    ///
+    /// ### Arguments
+    /// * `0` - The initial stop price
+    /// * `1` - The trailing distance (e.g., 4.0 to stay 4.0 away from the best price)
+    /// * `2` - The activation price (0.0 once activated, or to trail from entry)
+    TrailingStopDistance(f64, f64, f64),
 }
 
 impl OrderType {
-    /// Returns the price associated with the order type (for Market and Limit orders).
+    /// Returns the price associated with the order type (for Market, Limit and Stop orders).
+    ///
+    /// For a StopLimit order, this is the limit price.
     pub fn inner(&self) -> Result<f64> {
         match self {
-            Self::Market(price) | Self::Limit(price) => Ok(*price),
+            Self::Market(price) | Self::Limit(price) | Self::Stop(price) | Self::StopLimit(_, price) => Ok(*price),
             _ => Err(Error::MismatchedOrderType),
         }
     }
+
+    /// Returns the trigger price associated with the order type (for Stop and StopLimit orders).
+    pub fn stop_price(&self) -> Option<f64> {
+        match self {
+            Self::Stop(price) | Self::StopLimit(price, _) => Some(*price),
+            _ => None,
+        }
+    }
+}
+
+/// Represents the state of a pending order.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderState {
+    /// The order waits for its stop price to be reached and cannot be filled yet.
+    Armed,
+    /// The order can be filled (market and limit orders, or triggered stop orders).
+    Triggered,
+}
+
+impl From<&OrderType> for OrderState {
+    fn from(value: &OrderType) -> Self {
+        match value.stop_price() {
+            Some(_) => Self::Armed,
+            None => Self::Triggered,
+        }
+    }
+}
+
+/// Represents how long a pending order stays in the order book.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeInForce {
+    /// Good-till-cancelled: the order stays until it is filled or deleted.
+    ///
+    /// This is the default for limit, stop and stop-limit orders.
+    GoodTillCancelled,
+
+    /// Immediate-or-cancel: the order expires if it is not filled on the first candle it can be matched.
+    ///
+    /// This is the default for market orders. Stop orders are matched once triggered.
+    /// A partially filled order expires with its remaining quantity, when set explicitly.
+    /// Otherwise, the remaining quantity of a market order keeps working at the open of the next candles.
+    ImmediateOrCancel,
+
+    /// Fill-or-kill: the order expires if it is not entirely filled on the first candle it can be matched.
+    FillOrKill,
+
+    /// Good-till-bars: the order expires if it is not filled after the given number of candles.
+    ///
+    /// ### Arguments
+    /// * `0` - The number of candles.
+    GoodTillBars(usize),
+
+    /// Good-till-date: the order expires once a candle closes after the given time.
+    ///
+    /// ### Arguments
+    /// * `0` - The expiry time, compared to the candle close time.
+    GoodTillDate(DateTime<Utc>),
+}
+
+impl From<&OrderType> for TimeInForce {
+    fn from(value: &OrderType) -> Self {
+        match value {
+            OrderType::Market(_) => Self::ImmediateOrCancel,
+            _ => Self::GoodTillCancelled,
+        }
+    }
+}
+
+/// Represents whether a fill adds or removes liquidity from the market.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Liquidity {
+    /// The fill adds liquidity (limit orders and take-profits).
+    Maker,
+    /// The fill removes liquidity (market and stop orders, stop-losses and time stops).
+    Taker,
+}
+
+/// Represents when and at which price the orders placed by the strategy on a candle are filled.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ExecutionModel {
+    /// Orders are matched against the candle they are placed on, at their own price.
+    ///
+    /// A strategy reading the candle close can trade inside the same candle (look-ahead bias).
+    #[default]
+    CurrentBar,
+    /// Market orders are filled at the close of the candle they are placed on.
+    /// Other orders are matched from the next candle.
+    SameClose,
+    /// Orders are matched from the next candle, and market orders are filled at its open.
+    NextOpen,
+}
+
+/// Represents the delay before the orders and cancels sent by the strategy reach the market.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Latency {
+    /// The order is matched from the given number of candles after the candle it is sent on.
+    ///
+    /// ### Arguments
+    /// * `0` - The number of candles.
+    Bars(usize),
+
+    /// The order is matched from the first candle closing after the delay,
+    /// counted from the close of the candle it is sent on.
+    ///
+    /// ### Arguments
+    /// * `0` - The delay.
+    Duration(std::time::Duration),
+}
+
+/// Represents a time-based exit rule, closing the position at the candle close.
+///
+/// It is evaluated after the price exit rule, so both can be combined (e.g., a stop-loss and a time stop).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeStop {
+    /// Closes the position after the given number of candles.
+    ///
+    /// ### Arguments
+    /// * `0` - The number of candles after the entry candle.
+    Bars(usize),
+
+    /// Closes the position at the first candle closing at or after the given time (e.g., a session end).
+    ///
+    /// ### Arguments
+    /// * `0` - The exit time, compared to the candle close time.
+    Date(DateTime<Utc>),
 }
 
 /// Represents an order with entry and exit rules.
@@ -76,6 +295,28 @@ pub struct Order {
     /// Represents the buy/sell side of the order.
     pub side: OrderSide,
     exit_type: Option<OrderType>,
+    state: OrderState,
+    // Time-in-force set explicitly, the default of the entry type otherwise
+    time_in_force: Option<TimeInForce>,
+    time_stop: Option<TimeStop>,
+    // Number of candles the order has not been filled
+    bars: usize,
+    // Index of the candle on which the order was placed
+    placed_index: usize,
+    // True while the order has not reached the market yet
+    in_flight: bool,
+    // Index of the candle on which the order was cancelled, if it is being cancelled
+    cancel_index: Option<usize>,
+    // Orders sharing a group are cancelled when one of them is filled
+    group: Option<u32>,
+    // Position closed by this order when it is filled
+    position_id: Option<u32>,
+    // Position increased by this order when it is filled
+    add_to: Option<u32>,
+    // Child orders placed once this order is filled
+    legs: Vec<Order>,
+    // True for the remaining quantity of a partially filled order
+    remainder: bool,
 }
 
 impl PartialEq for Order {
@@ -88,11 +329,23 @@ type O1 = (OrderType, f64, OrderSide);
 impl From<O1> for Order {
     fn from((entry_type, quantity, side): O1) -> Self {
         Self {
-            id: random_id(),
+            id: 0,
+            state: OrderState::from(&entry_type),
+            time_in_force: None,
+            time_stop: None,
+            bars: 0,
+            placed_index: 0,
+            in_flight: false,
+            cancel_index: None,
             entry_type,
             quantity,
             side,
             exit_type: None,
+            group: None,
+            position_id: None,
+            add_to: None,
+            legs: Vec::new(),
+            remainder: false,
         }
     }
 }
@@ -101,21 +354,80 @@ type O2 = (OrderType, OrderType, f64, OrderSide);
 impl From<O2> for Order {
     fn from((entry_type, exit_type, quantity, side): O2) -> Self {
         Self {
-            id: random_id(),
+            id: 0,
+            state: OrderState::from(&entry_type),
+            time_in_force: None,
+            time_stop: None,
+            bars: 0,
+            placed_index: 0,
+            in_flight: false,
+            cancel_index: None,
             entry_type,
             quantity,
             side,
             exit_type: Some(exit_type),
+            group: None,
+            position_id: None,
+            add_to: None,
+            legs: Vec::new(),
+            remainder: false,
         }
     }
 }
 
 impl Order {
+    /// Returns the id of the order.
+    ///
+    /// The id is assigned by the backtest when the order is placed.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Updates the id of the order.
+    pub(crate) fn set_id(&mut self, id: u32) {
+        self.id = id;
+    }
+
     /// Returns the entry price of the order.
     pub fn entry_price(&self) -> Result<f64> {
         self.entry_type.inner()
     }
 
+    /// Updates the entry price of the order, keeping its entry type.
+    pub(crate) fn set_entry_price(&mut self, entry_price: f64) {
+        if let OrderType::Market(price)
+        | OrderType::Limit(price)
+        | OrderType::Stop(price)
+        | OrderType::StopLimit(_, price) = &mut self.entry_type
+        {
+            *price = entry_price;
+        }
+    }
+
+    /// Returns whether the order adds or removes liquidity when it is filled.
+    pub fn liquidity(&self) -> Liquidity {
+        if self.is_market_type() || self.is_stop_type() {
+            Liquidity::Taker
+        } else {
+            Liquidity::Maker
+        }
+    }
+
+    /// Returns the candle open as fill price when the candle opens beyond the order price.
+    ///
+    /// A limit order (or a triggered stop-limit) is filled at the better open price, a stop order at the worse one.
+    pub(crate) fn gap_price(&self, open: f64) -> Option<f64> {
+        let gapped = match (&self.entry_type, &self.side) {
+            (OrderType::Limit(price), OrderSide::Buy) | (OrderType::Stop(price), OrderSide::Sell) => open < *price,
+            (OrderType::Limit(price), OrderSide::Sell) | (OrderType::Stop(price), OrderSide::Buy) => open > *price,
+            //? a stop-limit rests as a limit order once triggered
+            (OrderType::StopLimit(_, price), OrderSide::Buy) if self.state == OrderState::Triggered => open < *price,
+            (OrderType::StopLimit(_, price), OrderSide::Sell) if self.state == OrderState::Triggered => open > *price,
+            _ => false,
+        };
+        gapped.then_some(open)
+    }
+
     /// Returns the total cost of the order (price * quantity).
     pub(crate) fn cost(&self) -> Result<f64> {
         let inner = self.entry_type.inner()?;
@@ -132,30 +444,296 @@ impl Order {
         &self.exit_type
     }
 
+    /// Returns the state of the order.
+    pub fn state(&self) -> &OrderState {
+        &self.state
+    }
+
+    /// Returns the stop price of the order, if any.
+    pub fn stop_price(&self) -> Option<f64> {
+        self.entry_type.stop_price()
+    }
+
+    /// Marks the order as triggered, so it can be filled.
+    pub(crate) fn trigger(&mut self) {
+        self.state = OrderState::Triggered;
+    }
+
+    /// Returns the time-in-force of the order.
+    pub fn time_in_force(&self) -> &TimeInForce {
+        match &self.time_in_force {
+            Some(time_in_force) => time_in_force,
+            None if self.is_market_type() => &TimeInForce::ImmediateOrCancel,
+            None => &TimeInForce::GoodTillCancelled,
+        }
+    }
+
+    /// Updates the time-in-force of the order.
+    pub fn set_time_in_force(&mut self, time_in_force: TimeInForce) {
+        self.time_in_force = Some(time_in_force);
+    }
+
+    /// Returns true if the time-in-force has been set explicitly.
+    pub(crate) fn has_time_in_force(&self) -> bool {
+        self.time_in_force.is_some()
+    }
+
+    /// Returns the time-based exit rule of the order.
+    pub fn time_stop(&self) -> Option<&TimeStop> {
+        self.time_stop.as_ref()
+    }
+
+    /// Updates the time-based exit rule, applied to the position once the order is filled.
+    pub fn set_time_stop(&mut self, time_stop: Option<TimeStop>) {
+        self.time_stop = time_stop;
+    }
+
+    /// Returns true if the order has expired at the given candle close time.
+    pub(crate) fn is_expired(&self, close_time: DateTime<Utc>) -> bool {
+        matches!(self.time_in_force(), TimeInForce::GoodTillDate(expiry) if close_time > *expiry)
+    }
+
+    /// Records a candle on which the order has not been filled.
+    ///
+    /// ### Returns
+    /// True if the order expires.
+    pub(crate) fn tick(&mut self) -> bool {
+        self.bars += 1;
+        match self.time_in_force() {
+            TimeInForce::ImmediateOrCancel | TimeInForce::FillOrKill => self.state == OrderState::Triggered,
+            TimeInForce::GoodTillBars(bars) => self.bars >= *bars,
+            _ => false,
+        }
+    }
+
+    /// Applies an amendment to the order.
+    pub(crate) fn amend(&mut self, amendment: Amendment) -> Result<()> {
+        match amendment {
+            Amendment::EntryType(entry_type) => {
+                if std::mem::discriminant(&entry_type) != std::mem::discriminant(&self.entry_type) {
+                    return Err(Error::MismatchedOrderType);
+                }
+                self.entry_type = entry_type;
+            }
+            Amendment::Quantity(quantity) => {
+                if quantity <= 0.0 || !quantity.is_finite() {
+                    return Err(Error::InvalidQuantity(quantity));
+                }
+                self.quantity = quantity;
+            }
+            Amendment::ExitRule(exit_type) => {
+                if exit_type.as_ref().is_some_and(|exit_type| exit_type.inner().is_ok()) {
+                    return Err(Error::MismatchedOrderType);
+                }
+                self.exit_type = exit_type;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the group of the order, if any.
+    pub(crate) fn group(&self) -> Option<u32> {
+        self.group
+    }
+
+    /// Links the order to a group, or unlinks it.
+    pub(crate) fn set_group(&mut self, group: Option<u32>) {
+        self.group = group;
+    }
+
+    /// Returns the id of the position closed by this order, if any.
+    pub(crate) fn position_id(&self) -> Option<u32> {
+        self.position_id
+    }
+
+    /// Makes the order close the given position when it is filled.
+    pub(crate) fn set_position_id(&mut self, position_id: u32) {
+        self.position_id = Some(position_id);
+    }
+
+    /// Returns the index of the candle on which the order was placed.
+    pub(crate) fn placed_index(&self) -> usize {
+        self.placed_index
+    }
+
+    /// Sets the index of the candle on which the order was placed.
+    pub(crate) fn set_placed_index(&mut self, placed_index: usize) {
+        self.placed_index = placed_index;
+    }
+
+    /// Returns true if the order has not reached the market yet.
+    pub(crate) fn in_flight(&self) -> bool {
+        self.in_flight
+    }
+
+    /// Updates whether the order has reached the market.
+    pub(crate) fn set_in_flight(&mut self, in_flight: bool) {
+        self.in_flight = in_flight;
+    }
+
+    /// Returns the index of the candle on which the order was cancelled, if it is being cancelled.
+    pub(crate) fn cancel_index(&self) -> Option<usize> {
+        self.cancel_index
+    }
+
+    /// Records a cancel sent on the given candle, effective once it reaches the market.
+    pub(crate) fn set_cancel_index(&mut self, cancel_index: usize) {
+        self.cancel_index = Some(cancel_index);
+    }
+
+    /// Returns the id of the position increased by this order, if any.
+    pub(crate) fn add_to(&self) -> Option<u32> {
+        self.add_to
+    }
+
+    /// Sets the id of the position increased by this order.
+    pub(crate) fn set_add_to(&mut self, position_id: u32) {
+        self.add_to = Some(position_id);
+    }
+
+    /// Returns true if the order is the remaining quantity of a partially filled order.
+    pub(crate) fn is_remainder(&self) -> bool {
+        self.remainder
+    }
+
+    /// Marks the order as the remaining quantity of a partially filled order.
+    pub(crate) fn set_remainder(&mut self) {
+        self.remainder = true;
+    }
+
+    /// Takes the child orders to place once this order is filled.
+    pub(crate) fn take_legs(&mut self) -> Vec<Order> {
+        std::mem::take(&mut self.legs)
+    }
+
     /// Returns true if it is a market order, and false if it is a limit order.
     pub fn is_market_type(&self) -> bool {
         matches!(self.entry_type, OrderType::Market(_))
     }
 
+    /// Returns true if it is a stop order.
+    pub fn is_stop_type(&self) -> bool {
+        matches!(self.entry_type, OrderType::Stop(_))
+    }
+
     /// Updates the trailing stop price for the order.
+    ///
+    /// Trailing stops with an activation price are only updated once the new price reaches it.
     pub fn set_trailingstop(&mut self, new_price: f64) {
-        if let Some(OrderType::TrailingStop(current_price, _)) = &mut self.exit_type {
-            match self.side {
-                OrderSide::Buy => {
-                    if new_price > *current_price {
-                        *current_price = new_price;
-                    }
+        let (current_price, activation) = match &mut self.exit_type {
+            Some(OrderType::TrailingStop(current_price, _)) => (current_price, None),
+            Some(OrderType::TrailingStopActivation(current_price, _, activation))
+            | Some(OrderType::TrailingStopDistance(current_price, _, activation)) => (current_price, Some(activation)),
+            _ => return,
+        };
+
+        if let Some(activation) = activation
+            && *activation > 0.0
+        {
+            let activated = match self.side {
+                OrderSide::Buy => new_price >= *activation,
+                OrderSide::Sell => new_price <= *activation,
+            };
+            if !activated {
+                return;
+            }
+            *activation = 0.0;
+        }
+
+        match self.side {
+            OrderSide::Buy => {
+                if new_price > *current_price {
+                    *current_price = new_price;
                 }
-                OrderSide::Sell => {
-                    if new_price < *current_price {
-                        *current_price = new_price;
-                    }
+            }
+            OrderSide::Sell => {
+                if new_price < *current_price {
+                    *current_price = new_price;
                 }
             }
         }
     }
 }
 
+/// Represents a change to apply to a pending order or an open position.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Amendment {
+    /// Changes the entry prices of a pending order.
+    ///
+    /// The order type must stay the same (e.g., a limit order can only get a new limit price).
+    ///
+    /// ### Arguments
+    /// * `0` - The new entry type.
+    EntryType(OrderType),
+
+    /// Changes the quantity of a pending order.
+    ///
+    /// ### Arguments
+    /// * `0` - The new quantity.
+    Quantity(f64),
+
+    /// Changes the exit rule of a pending order or an open position.
+    ///
+    /// ### Arguments
+    /// * `0` - The new exit rule (`None` to remove it).
+    ExitRule(Option<OrderType>),
+}
+
+/// Represents a group of linked orders.
+#[derive(Debug, Clone)]
+pub enum OrderGroup {
+    /// One-cancels-other group.
+    ///
+    /// When one of the orders is filled, the other orders are cancelled and their funds unlocked.
+    ///
+    /// ### Arguments
+    /// * `0` - The linked orders.
+    Oco(Vec<Order>),
+
+    /// Bracket group: an entry order with attached take-profit and stop-loss legs.
+    ///
+    /// Once the entry is filled, the legs are placed in the order book as one-cancels-other
+    /// child orders (a limit order for the take-profit, a stop order for the stop-loss),
+    /// and the position is closed when one of them is filled.
+    ///
+    /// ### Arguments
+    /// * `0` - The entry order.
+    /// * `1` - The take-profit price (0.0 to disable)
+    /// * `2` - The stop-loss price (0.0 to disable)
+    Bracket(Order, f64, f64),
+}
+
+impl OrderGroup {
+    /// Links the orders of the group together and returns the orders to place.
+    pub(crate) fn into_orders(self, ids: &mut IdGenerator) -> Result<Vec<Order>> {
+        let group = ids.next_id();
+        match self {
+            Self::Oco(mut orders) => {
+                orders.iter_mut().for_each(|order| order.set_group(Some(group)));
+                Ok(orders)
+            }
+            Self::Bracket(mut order, take_profit, stop_loss) => {
+                if take_profit < 0.0 || stop_loss < 0.0 {
+                    return Err(Error::NegTakeProfitAndStopLoss);
+                }
+
+                let side = order.side.opposite();
+                let legs = [OrderType::Limit(take_profit), OrderType::Stop(stop_loss)]
+                    .into_iter()
+                    .filter(|leg| leg.inner().is_ok_and(|price| price > 0.0))
+                    .map(|leg| {
+                        let mut leg = Order::from((leg, order.quantity, side.clone()));
+                        leg.set_group(Some(group));
+                        leg
+                    });
+                order.legs = legs.collect();
+                Ok(vec![order])
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 #[test]
 fn create_simple_order() {
@@ -193,8 +771,11 @@ fn create_order_with_exit_rule() {
 #[cfg(test)]
 #[test]
 fn order_equality() {
-    let order1: Order = (OrderType::Market(100.0), 1.0, OrderSide::Buy).into();
-    let order2: Order = (OrderType::Market(100.0), 1.0, OrderSide::Buy).into();
+    let mut ids = IdGenerator::default();
+    let mut order1: Order = (OrderType::Market(100.0), 1.0, OrderSide::Buy).into();
+    order1.set_id(ids.next_id());
+    let mut order2: Order = (OrderType::Market(100.0), 1.0, OrderSide::Buy).into();
+    order2.set_id(ids.next_id());
     assert_ne!(order1, order2);
     assert_eq!(order1, order1);
 }
@@ -209,6 +790,132 @@ fn entry_price() {
     assert_eq!(limit_order.entry_price().unwrap(), 150.0);
 }
 
+#[cfg(test)]
+#[test]
+fn stop_order() {
+    let order: Order = (OrderType::Stop(120.0), 2.0, OrderSide::Buy).into();
+    assert_eq!(order.entry_price().unwrap(), 120.0);
+    assert_eq!(order.cost().unwrap(), 240.0);
+    assert!(order.is_stop_type());
+    assert!(!order.is_market_type());
+}
+
+#[cfg(test)]
+#[test]
+fn stop_limit_order() {
+    let mut order: Order = (OrderType::StopLimit(120.0, 121.0), 2.0, OrderSide::Buy).into();
+    assert_eq!(order.entry_price().unwrap(), 121.0);
+    assert_eq!(order.stop_price(), Some(120.0));
+    assert_eq!(order.cost().unwrap(), 242.0);
+    assert_eq!(order.state(), &OrderState::Armed);
+    assert!(!order.is_stop_type());
+
+    order.trigger();
+    assert_eq!(order.state(), &OrderState::Triggered);
+
+    let order: Order = (OrderType::Limit(120.0), 2.0, OrderSide::Buy).into();
+    assert_eq!(order.stop_price(), None);
+    assert_eq!(order.state(), &OrderState::Triggered);
+}
+
+#[cfg(test)]
+#[test]
+fn oco_group() {
+    let order1: Order = (OrderType::Stop(120.0), 1.0, OrderSide::Buy).into();
+    let order2: Order = (OrderType::Stop(80.0), 1.0, OrderSide::Sell).into();
+    let orders = OrderGroup::Oco(vec![order1, order2])
+        .into_orders(&mut IdGenerator::default())
+        .unwrap();
+
+    assert_eq!(orders.len(), 2);
+    assert!(orders[0].group().is_some());
+    assert_eq!(orders[0].group(), orders[1].group());
+}
+
+#[cfg(test)]
+#[test]
+fn bracket_group() {
+    let order: Order = (OrderType::Limit(100.0), 2.0, OrderSide::Buy).into();
+    let mut orders = OrderGroup::Bracket(order, 120.0, 90.0)
+        .into_orders(&mut IdGenerator::default())
+        .unwrap();
+
+    assert_eq!(orders.len(), 1);
+    let legs = orders[0].take_legs();
+    assert_eq!(legs.len(), 2);
+    assert_eq!(legs[0].entry_type(), &OrderType::Limit(120.0));
+    assert_eq!(legs[1].entry_type(), &OrderType::Stop(90.0));
+    assert!(
+        legs.iter()
+            .all(|leg| matches!(leg.side, OrderSide::Sell) && leg.quantity == 2.0)
+    );
+    assert_eq!(legs[0].group(), legs[1].group());
+
+    let order: Order = (OrderType::Limit(100.0), 2.0, OrderSide::Buy).into();
+    let mut orders = OrderGroup::Bracket(order, 120.0, 0.0)
+        .into_orders(&mut IdGenerator::default())
+        .unwrap();
+    assert_eq!(orders[0].take_legs().len(), 1);
+
+    let order: Order = (OrderType::Limit(100.0), 2.0, OrderSide::Buy).into();
+    assert!(
+        OrderGroup::Bracket(order, -1.0, 0.0)
+            .into_orders(&mut IdGenerator::default())
+            .is_err()
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn amend_order() {
+    let mut order: Order = (OrderType::Limit(100.0), 1.0, OrderSide::Buy).into();
+
+    order.amend(Amendment::EntryType(OrderType::Limit(95.0))).unwrap();
+    assert_eq!(order.entry_price().unwrap(), 95.0);
+
+    order.amend(Amendment::Quantity(2.0)).unwrap();
+    assert_eq!(order.cost().unwrap(), 190.0);
+
+    let exit_rule = OrderType::TakeProfitAndStopLoss(120.0, 90.0);
+    order.amend(Amendment::ExitRule(Some(exit_rule.clone()))).unwrap();
+    assert_eq!(order.exit_rule(), &Some(exit_rule));
+
+    let result = order.amend(Amendment::EntryType(OrderType::Market(95.0)));
+    assert!(matches!(result, Err(Error::MismatchedOrderType)));
+    let result = order.amend(Amendment::Quantity(0.0));
+    assert!(matches!(result, Err(Error::InvalidQuantity(_))));
+    let result = order.amend(Amendment::ExitRule(Some(OrderType::Limit(120.0))));
+    assert!(matches!(result, Err(Error::MismatchedOrderType)));
+}
+
+#[cfg(test)]
+#[test]
+fn time_in_force() {
+    let mut order: Order = (OrderType::Market(100.0), 1.0, OrderSide::Buy).into();
+    assert_eq!(order.time_in_force(), &TimeInForce::ImmediateOrCancel);
+    assert!(order.tick());
+
+    let mut order: Order = (OrderType::Limit(100.0), 1.0, OrderSide::Buy).into();
+    assert_eq!(order.time_in_force(), &TimeInForce::GoodTillCancelled);
+    assert!(!order.tick());
+
+    let mut order: Order = (OrderType::Limit(100.0), 1.0, OrderSide::Buy).into();
+    order.set_time_in_force(TimeInForce::GoodTillBars(2));
+    assert!(!order.tick());
+    assert!(order.tick());
+
+    let mut order: Order = (OrderType::Stop(100.0), 1.0, OrderSide::Buy).into();
+    order.set_time_in_force(TimeInForce::FillOrKill);
+    assert!(!order.tick());
+    order.trigger();
+    assert!(order.tick());
+
+    let expiry = DateTime::from_timestamp_secs(1515151515).unwrap();
+    order.set_time_in_force(TimeInForce::GoodTillDate(expiry));
+    assert!(!order.is_expired(expiry));
+    assert!(order.is_expired(DateTime::from_timestamp_secs(1515151516).unwrap()));
+}
+
 #[cfg(test)]
 #[test]
 fn order_cost() {
@@ -271,6 +978,44 @@ fn set_trailingstop_sell() {
     }
 }
 
+#[cfg(test)]
+#[test]
+fn set_trailingstop_activation() {
+    let mut order: Order = (
+        OrderType::Market(100.0),
+        OrderType::TrailingStopDistance(100.0, 4.0, 103.0),
+        1.0,
+        OrderSide::Buy,
+    )
+        .into();
+
+    order.set_trailingstop(102.0);
+    assert_eq!(
+        order.exit_rule(),
+        &Some(OrderType::TrailingStopDistance(100.0, 4.0, 103.0))
+    );
+
+    order.set_trailingstop(104.0);
+    assert_eq!(
+        order.exit_rule(),
+        &Some(OrderType::TrailingStopDistance(104.0, 4.0, 0.0))
+    );
+
+    let mut order: Order = (
+        OrderType::Market(100.0),
+        OrderType::TrailingStopActivation(100.0, 1.0, 97.0),
+        1.0,
+        OrderSide::Sell,
+    )
+        .into();
+
+    order.set_trailingstop(96.0);
+    assert_eq!(
+        order.exit_rule(),
+        &Some(OrderType::TrailingStopActivation(96.0, 1.0, 0.0))
+    );
+}
+
 #[cfg(test)]
 #[test]
 fn set_trailingstop_no_exit_rule() {
@@ -296,3 +1041,19 @@ fn order_type_inner_panics() {
     let take_profit_order = OrderType::TakeProfitAndStopLoss(120.0, 90.0);
     take_profit_order.inner().unwrap();
 }
+
+#[cfg(test)]
+#[test]
+fn gap_price_triggered_stop_limit() {
+    let mut order: Order = (OrderType::StopLimit(105.0, 110.0), 1.0, OrderSide::Buy).into();
+    assert_eq!(order.gap_price(100.0), None);
+
+    order.trigger();
+    assert_eq!(order.gap_price(100.0), Some(100.0));
+    assert_eq!(order.gap_price(112.0), None);
+
+    let mut order: Order = (OrderType::StopLimit(95.0, 90.0), 1.0, OrderSide::Sell).into();
+    order.trigger();
+    assert_eq!(order.gap_price(92.0), Some(92.0));
+    assert_eq!(order.gap_price(88.0), None);
+}
@@ -1,5 +1,10 @@
-use super::order::{Order, OrderSide};
-use crate::{errors::*, utils::random_id};
+use chrono::{DateTime, Utc};
+
+use super::{
+    candle::Candle,
+    order::{Liquidity, Order, OrderSide, TimeStop},
+};
+use crate::errors::*;
 
 /// Represents the side of a position (long or short).
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -11,6 +16,178 @@ pub enum PositionSide {
     Short,
 }
 
+impl PositionSide {
+    /// Returns the side of the orders closing the position.
+    pub(crate) fn exit_side(&self) -> OrderSide {
+        match self {
+            PositionSide::Long => OrderSide::Sell,
+            PositionSide::Short => OrderSide::Buy,
+        }
+    }
+}
+
+/// Represents how filled orders are turned into positions.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum AccountMode {
+    /// Every filled order opens an independent position, so long and short positions can be open at once.
+    #[default]
+    Hedge,
+    /// A single net position: orders on the same side increase it with an averaged entry price,
+    /// and opposite orders reduce or flip it.
+    OneWay,
+}
+
+/// Represents the funding rates paid between long and short positions of perpetual futures.
+///
+/// At each funding time, longs pay `rate × notional` to shorts (shorts pay longs when the rate is negative).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Funding {
+    /// Funding rates at the given times.
+    ///
+    /// ### Arguments
+    /// * `0` - The funding times with their rates (e.g., 0.0001 for 0.01%).
+    Series(Vec<(DateTime<Utc>, f64)>),
+
+    /// A constant funding rate paid at every multiple of the interval since the Unix epoch.
+    ///
+    /// ### Arguments
+    /// * `0` - The funding rate.
+    /// * `1` - The interval (e.g., 8 hours for funding at 00:00, 08:00 and 16:00 UTC).
+    Constant(f64, std::time::Duration),
+}
+
+impl Funding {
+    /// Returns the funding rates of the funding times after `start` and until `end` included.
+    pub(crate) fn rates(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<f64> {
+        match self {
+            Funding::Series(rates) => rates
+                .iter()
+                .filter(|(time, _)| start < *time && *time <= end)
+                .map(|(_, rate)| *rate)
+                .collect(),
+            Funding::Constant(rate, interval) => {
+                let interval = interval.as_millis() as i64;
+                if interval <= 0 {
+                    return Vec::new();
+                }
+                let count = end.timestamp_millis().div_euclid(interval) - start.timestamp_millis().div_euclid(interval);
+                vec![*rate; count.max(0) as usize]
+            }
+        }
+    }
+}
+
+/// Represents the assumed price path inside a candle, when it reaches both the take-profit and the stop-loss.
+///
+/// A candle opening beyond one of the prices reaches it first, whatever the path.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default)]
+pub enum IntrabarPath {
+    /// The take-profit is reached first.
+    #[default]
+    Optimistic,
+    /// The stop-loss is reached first.
+    Pessimistic,
+    /// The price goes from the open to the nearer extreme, then to the other extreme and the close.
+    OhlcHeuristic,
+    /// The lower-timeframe candles inside the candle are replayed in order to find the first price reached.
+    ///
+    /// Falls back to the OHLC heuristic when a lower-timeframe candle also reaches both prices,
+    /// or when no lower-timeframe candle resolves it.
+    ///
+    /// ### Arguments
+    /// * `0` - The lower-timeframe candles, sorted by open time.
+    LowerTimeframe(Vec<Candle>),
+}
+
+impl IntrabarPath {
+    /// Returns true if the take-profit is reached before the stop-loss, for a candle reaching both.
+    pub(crate) fn take_profit_first(
+        &self,
+        side: &PositionSide,
+        take_profit: f64,
+        stop_loss: f64,
+        candle: &Candle,
+    ) -> bool {
+        //? the candle opens beyond one of the prices, which is reached at the open
+        let (open_past_take_profit, open_past_stop_loss) = match side {
+            PositionSide::Long => (candle.open() >= take_profit, candle.open() <= stop_loss),
+            PositionSide::Short => (candle.open() <= take_profit, candle.open() >= stop_loss),
+        };
+        if open_past_take_profit || open_past_stop_loss {
+            return open_past_take_profit;
+        }
+        match self {
+            Self::Optimistic => true,
+            Self::Pessimistic => false,
+            Self::OhlcHeuristic => {
+                //? ties are resolved pessimistically
+                let high_distance = candle.high() - candle.open();
+                let low_distance = candle.open() - candle.low();
+                match side {
+                    PositionSide::Long => high_distance < low_distance,
+                    PositionSide::Short => low_distance < high_distance,
+                }
+            }
+            Self::LowerTimeframe(candles) => {
+                let start = candles.partition_point(|c| c.open_time() < candle.open_time());
+                candles[start..]
+                    .iter()
+                    .take_while(|c| c.close_time() <= candle.close_time())
+                    .find_map(|c| match exits_reached(side, take_profit, stop_loss, c) {
+                        (true, false) => Some(true),
+                        (false, true) => Some(false),
+                        (true, true) => Some(Self::OhlcHeuristic.take_profit_first(side, take_profit, stop_loss, c)),
+                        (false, false) => None,
+                    })
+                    .unwrap_or_else(|| Self::OhlcHeuristic.take_profit_first(side, take_profit, stop_loss, candle))
+            }
+        }
+    }
+}
+
+/// Returns whether the take-profit and the stop-loss are reached inside the candle (0.0 disables a price).
+pub(crate) fn exits_reached(side: &PositionSide, take_profit: f64, stop_loss: f64, candle: &Candle) -> (bool, bool) {
+    match side {
+        PositionSide::Long => (
+            take_profit > 0.0 && take_profit <= candle.high(),
+            stop_loss > 0.0 && stop_loss >= candle.low(),
+        ),
+        PositionSide::Short => (
+            take_profit > 0.0 && take_profit >= candle.low(),
+            stop_loss > 0.0 && stop_loss <= candle.high(),
+        ),
+    }
+}
+
+/// Returns the worst price reached by a position inside the candle (the low for a long, the high for a short).
+pub(crate) fn adverse_price(side: &PositionSide, candle: &Candle) -> f64 {
+    match side {
+        PositionSide::Long => candle.low(),
+        PositionSide::Short => candle.high(),
+    }
+}
+
+/// Returns the fill of a stop exit, at the candle open when the candle opens beyond the stop.
+pub(crate) fn stop_fill(side: &PositionSide, stop_price: f64, candle: &Candle) -> (f64, Liquidity) {
+    let price = match side {
+        PositionSide::Long => stop_price.min(candle.open()),
+        PositionSide::Short => stop_price.max(candle.open()),
+    };
+    (price, Liquidity::Taker)
+}
+
+/// Returns the fill of a take-profit exit, at the candle open when the candle opens beyond the take-profit.
+pub(crate) fn take_profit_fill(side: &PositionSide, take_profit: f64, candle: &Candle) -> (f64, Liquidity) {
+    let price = match side {
+        PositionSide::Long => take_profit.max(candle.open()),
+        PositionSide::Short => take_profit.min(candle.open()),
+    };
+    (price, Liquidity::Maker)
+}
+
 /// Represents a trading position with an associated order.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone)]
@@ -19,6 +196,10 @@ pub struct Position {
     order: Order,
     /// The side of the position, either long or short.
     pub side: PositionSide,
+    // Index of the candle on which the position was opened
+    entry_index: usize,
+    // Number of filled orders merged into the position
+    entries: usize,
     #[cfg(feature = "metrics")]
     exit_price: Option<f64>,
 }
@@ -32,7 +213,9 @@ impl PartialEq for Position {
 impl From<Order> for Position {
     fn from(value: Order) -> Self {
         Self {
-            id: random_id(),
+            id: 0,
+            entry_index: 0,
+            entries: 1,
             #[cfg(feature = "metrics")]
             exit_price: None,
             order: value.clone(),
@@ -58,6 +241,79 @@ impl std::ops::DerefMut for Position {
 }
 
 impl Position {
+    /// Returns the id of the position.
+    ///
+    /// The id is assigned by the backtest when the position is opened.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Updates the id of the position.
+    pub(crate) fn set_id(&mut self, id: u32) {
+        self.id = id;
+    }
+
+    /// Returns the index of the candle on which the position was opened.
+    pub fn entry_index(&self) -> usize {
+        self.entry_index
+    }
+
+    /// Updates the index of the candle on which the position was opened.
+    pub(crate) fn set_entry_index(&mut self, entry_index: usize) {
+        self.entry_index = entry_index;
+    }
+
+    /// Returns the number of filled orders merged into the position (1 for a position never added to).
+    pub fn entries(&self) -> usize {
+        self.entries
+    }
+
+    /// Increases the position with a new entry, averaging the entry price.
+    ///
+    /// ### Arguments
+    /// * `quantity` - The added quantity.
+    /// * `price` - The entry price of the added quantity.
+    pub(crate) fn increase(&mut self, quantity: f64, price: f64) -> Result<()> {
+        self.merge(quantity, price)?;
+        self.entries += 1;
+        Ok(())
+    }
+
+    /// Merges a fill of an existing entry (e.g., a partial fill), averaging the entry price.
+    ///
+    /// ### Arguments
+    /// * `quantity` - The added quantity.
+    /// * `price` - The entry price of the added quantity.
+    pub(crate) fn merge(&mut self, quantity: f64, price: f64) -> Result<()> {
+        if quantity <= 0.0 || !quantity.is_finite() {
+            return Err(Error::InvalidQuantity(quantity));
+        }
+        let total_quantity = self.quantity + quantity;
+        let entry_price = (self.entry_price()? * self.quantity + price * quantity) / total_quantity;
+        self.set_entry_price(entry_price);
+        self.quantity = total_quantity;
+        Ok(())
+    }
+
+    /// Returns true if the time-based exit rule is reached at the given candle.
+    ///
+    /// ### Arguments
+    /// * `index` - The index of the current candle.
+    /// * `close_time` - The close time of the current candle.
+    pub(crate) fn is_time_stopped(&self, index: usize, close_time: DateTime<Utc>) -> bool {
+        match self.time_stop() {
+            Some(TimeStop::Bars(bars)) => index >= self.entry_index + bars,
+            Some(TimeStop::Date(exit_time)) => close_time >= *exit_time,
+            None => false,
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    /// Returns the fill price at which the position was closed, set in the `DelPosition` event.
+    pub fn exit_price(&self) -> Option<f64> {
+        self.exit_price
+    }
+
     #[cfg(feature = "metrics")]
     /// Updates the `exit_price`.
     pub(crate) fn set_exit_price(&mut self, exit_price: f64) -> Result<()> {
@@ -88,6 +344,113 @@ impl Position {
 #[cfg(test)]
 use super::order::OrderType;
 
+#[cfg(test)]
+fn get_candle(open: f64, high: f64, low: f64, close: f64, open_time: i64) -> Candle {
+    use super::candle::CandleBuilder;
+
+    CandleBuilder::builder()
+        .open(open)
+        .high(high)
+        .low(low)
+        .close(close)
+        .volume(1.0)
+        .open_time(DateTime::from_timestamp_secs(open_time).unwrap())
+        .close_time(DateTime::from_timestamp_secs(open_time + 1).unwrap())
+        .build()
+        .unwrap()
+}
+
+#[cfg(test)]
+#[test]
+fn intrabar_path_ohlc_heuristic() {
+    // the high is nearer to the open
+    let candle = get_candle(100.0, 105.0, 90.0, 95.0, 0);
+    let path = IntrabarPath::OhlcHeuristic;
+    assert!(path.take_profit_first(&PositionSide::Long, 104.0, 91.0, &candle));
+    assert!(!path.take_profit_first(&PositionSide::Short, 91.0, 104.0, &candle));
+
+    // ties are pessimistic
+    let candle = get_candle(100.0, 110.0, 90.0, 95.0, 0);
+    assert!(!path.take_profit_first(&PositionSide::Long, 104.0, 91.0, &candle));
+    assert!(!path.take_profit_first(&PositionSide::Short, 91.0, 104.0, &candle));
+
+    // the candle opens beyond the stop-loss, although the high is nearer to the open
+    let candle = get_candle(94.0, 111.0, 60.0, 100.0, 0);
+    assert!(!path.take_profit_first(&PositionSide::Long, 110.0, 95.0, &candle));
+    assert!(!IntrabarPath::Optimistic.take_profit_first(&PositionSide::Long, 110.0, 95.0, &candle));
+    // the candle opens beyond the take-profit
+    assert!(path.take_profit_first(&PositionSide::Short, 95.0, 110.0, &candle));
+}
+
+#[cfg(test)]
+#[test]
+fn intrabar_path_lower_timeframe() {
+    use super::candle::CandleBuilder;
+
+    let candle = CandleBuilder::builder()
+        .open(100.0)
+        .high(110.0)
+        .low(90.0)
+        .close(95.0)
+        .volume(1.0)
+        .open_time(DateTime::from_timestamp_secs(10).unwrap())
+        .close_time(DateTime::from_timestamp_secs(13).unwrap())
+        .build()
+        .unwrap();
+    let path = IntrabarPath::LowerTimeframe(vec![
+        // outside of the candle
+        get_candle(100.0, 110.0, 100.0, 105.0, 9),
+        get_candle(100.0, 102.0, 98.0, 101.0, 10),
+        get_candle(101.0, 101.0, 90.0, 92.0, 11),
+        get_candle(92.0, 110.0, 92.0, 95.0, 12),
+    ]);
+    assert!(!path.take_profit_first(&PositionSide::Long, 104.0, 91.0, &candle));
+    assert!(path.take_profit_first(&PositionSide::Short, 91.0, 104.0, &candle));
+
+    // no lower-timeframe candle, falls back to the OHLC heuristic
+    let path = IntrabarPath::LowerTimeframe(Vec::new());
+    assert!(!path.take_profit_first(&PositionSide::Long, 104.0, 91.0, &candle));
+}
+
+#[cfg(test)]
+#[test]
+fn increase_position_averages_entry_price() {
+    let order: Order = (OrderType::Limit(100.0), 1.0, OrderSide::Buy).into();
+    let mut position = Position::from(order);
+
+    position.increase(3.0, 120.0).unwrap();
+    assert_eq!(position.quantity, 4.0);
+    assert_eq!(position.entry_price().unwrap(), 115.0);
+    assert_eq!(position.entry_type(), &OrderType::Limit(115.0));
+    assert_eq!(position.entries(), 2);
+    assert!(matches!(
+        position.increase(0.0, 120.0),
+        Err(Error::InvalidQuantity(0.0))
+    ));
+
+    position.merge(4.0, 105.0).unwrap();
+    assert_eq!(position.quantity, 8.0);
+    assert_eq!(position.entry_price().unwrap(), 110.0);
+    assert_eq!(position.entries(), 2);
+}
+
+#[cfg(test)]
+#[test]
+fn position_time_stop() {
+    let mut order: Order = (OrderType::Market(100.0), 1.0, OrderSide::Buy).into();
+    order.set_time_stop(Some(TimeStop::Bars(2)));
+    let mut position = Position::from(order);
+    position.set_entry_index(3);
+
+    let close_time = DateTime::from_timestamp_secs(1515151516).unwrap();
+    assert!(!position.is_time_stopped(4, close_time));
+    assert!(position.is_time_stopped(5, close_time));
+
+    position.set_time_stop(Some(TimeStop::Date(close_time)));
+    assert!(!position.is_time_stopped(3, DateTime::from_timestamp_secs(1515151515).unwrap()));
+    assert!(position.is_time_stopped(3, close_time));
+}
+
 #[cfg(test)]
 #[test]
 fn create_position_from_buy_order() {
@@ -116,9 +479,11 @@ fn create_position_from_sell_order() {
 #[test]
 fn position_equality() {
     let order1: Order = (OrderType::Market(100.0), 1.0, OrderSide::Buy).into();
-    let position1 = Position::from(order1);
+    let mut position1 = Position::from(order1);
+    position1.set_id(1);
     let order2: Order = (OrderType::Market(100.0), 1.0, OrderSide::Buy).into();
-    let position2 = Position::from(order2);
+    let mut position2 = Position::from(order2);
+    position2.set_id(2);
 
     assert_ne!(position1, position2);
     assert_eq!(position1, position1);
@@ -127,10 +492,13 @@ fn position_equality() {
 #[cfg(test)]
 #[test]
 fn position_ids_are_unique() {
+    let mut ids = crate::utils::IdGenerator::default();
     let order1: Order = (OrderType::Market(100.0), 1.0, OrderSide::Buy).into();
-    let position1 = Position::from(order1);
+    let mut position1 = Position::from(order1);
+    position1.set_id(ids.next_id());
     let order2: Order = (OrderType::Market(100.0), 1.0, OrderSide::Buy).into();
-    let position2 = Position::from(order2);
+    let mut position2 = Position::from(order2);
+    position2.set_id(ids.next_id());
 
     assert_ne!(position1.id, position2.id);
 }
@@ -218,3 +586,16 @@ fn position_set_trailingstop() {
         panic!("Expected TrailingStop order type");
     }
 }
+
+#[cfg(test)]
+#[test]
+fn funding_rates() {
+    let time = |secs| DateTime::from_timestamp_secs(secs).unwrap();
+    let funding = Funding::Series(vec![(time(100), 0.1), (time(200), -0.2), (time(300), 0.3)]);
+    assert_eq!(funding.rates(time(100), time(200)), vec![-0.2]);
+    assert_eq!(funding.rates(time(0), time(300)), vec![0.1, -0.2, 0.3]);
+
+    let funding = Funding::Constant(0.1, std::time::Duration::from_secs(8 * 3600));
+    assert_eq!(funding.rates(time(0), time(8 * 3600 - 1)), Vec::<f64>::new());
+    assert_eq!(funding.rates(time(3600), time(24 * 3600)), vec![0.1; 3]);
+}
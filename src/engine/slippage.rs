@@ -0,0 +1,109 @@
+use super::candle::Candle;
+
+/// Trait for modeling the slippage of the fills removing liquidity (market and stop orders, stop exits).
+///
+/// The slippage is an adverse price move: buys are filled higher and sells lower.
+pub trait Slippage: std::fmt::Debug + Send + Sync {
+    /// Returns the slippage of a fill, as a positive price distance.
+    ///
+    /// ### Arguments
+    /// * `price` - The price requested by the order.
+    /// * `quantity` - The filled quantity.
+    /// * `candle` - The candle on which the order is filled.
+    fn slippage(&self, price: f64, quantity: f64, candle: &Candle) -> f64;
+}
+
+/// Fixed slippage in basis points of the price.
+///
+/// ### Arguments
+/// * `0` - The basis points (e.g., 5.0 for 0.05%).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct FixedBps(pub f64);
+
+impl Slippage for FixedBps {
+    fn slippage(&self, price: f64, _quantity: f64, _candle: &Candle) -> f64 {
+        price * self.0 / 10_000.0
+    }
+}
+
+/// Fixed slippage in ticks.
+///
+/// ### Arguments
+/// * `0` - The number of ticks.
+/// * `1` - The tick size (e.g., 0.01).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct FixedTicks(pub u32, pub f64);
+
+impl Slippage for FixedTicks {
+    fn slippage(&self, _price: f64, _quantity: f64, _candle: &Candle) -> f64 {
+        self.0 as f64 * self.1
+    }
+}
+
+/// Slippage as a fraction of the candle range (high - low).
+///
+/// ### Arguments
+/// * `0` - The fraction of the range (e.g., 0.1 for 10%).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct RangeFraction(pub f64);
+
+impl Slippage for RangeFraction {
+    fn slippage(&self, _price: f64, _quantity: f64, candle: &Candle) -> f64 {
+        (candle.high() - candle.low()) * self.0
+    }
+}
+
+/// Slippage growing with the filled quantity relative to the candle volume.
+///
+/// The slippage is `price × impact × quantity / volume`, capped when the quantity exceeds the volume
+/// (or when the candle has no volume).
+///
+/// ### Arguments
+/// * `0` - The impact of a fill of the whole candle volume (e.g., 0.01 for 1% of the price).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct VolumeImpact(pub f64);
+
+impl Slippage for VolumeImpact {
+    fn slippage(&self, price: f64, quantity: f64, candle: &Candle) -> f64 {
+        let participation = if candle.volume() > 0.0 {
+            (quantity / candle.volume()).min(1.0)
+        } else {
+            1.0
+        };
+        price * self.0 * participation
+    }
+}
+
+#[cfg(test)]
+use super::candle::CandleBuilder;
+
+#[cfg(test)]
+fn get_candle() -> Candle {
+    use chrono::DateTime;
+
+    CandleBuilder::builder()
+        .open(100.0)
+        .high(110.0)
+        .low(90.0)
+        .close(105.0)
+        .volume(10.0)
+        .open_time(DateTime::from_timestamp_secs(1515151515).unwrap())
+        .close_time(DateTime::from_timestamp_secs(1515151516).unwrap())
+        .build()
+        .unwrap()
+}
+
+#[cfg(test)]
+#[test]
+fn builtin_slippages() {
+    let candle = get_candle();
+    assert_eq!(FixedBps(50.0).slippage(200.0, 1.0, &candle), 1.0);
+    assert_eq!(FixedTicks(4, 0.25).slippage(200.0, 1.0, &candle), 1.0);
+    assert_eq!(RangeFraction(0.1).slippage(200.0, 1.0, &candle), 2.0);
+    assert_eq!(VolumeImpact(0.01).slippage(200.0, 5.0, &candle), 1.0);
+    assert_eq!(VolumeImpact(0.01).slippage(200.0, 50.0, &candle), 2.0);
+}
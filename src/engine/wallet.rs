@@ -14,6 +14,16 @@ pub struct Wallet {
     unrealized_pnl: f64,
     // Cumulative fees paid
     fees: f64,
+    // Cumulative slippage cost
+    slippage: f64,
+    // Cumulative funding paid (negative when received)
+    funding: f64,
+    // Cumulative borrow fees paid by the short positions
+    borrow: f64,
+    // Collateral of the open short positions (proceeds and initial margin)
+    short_collateral: f64,
+    // Cumulative losses of the liquidations not covered by the balance
+    shortfall: f64,
 }
 
 impl Wallet {
@@ -27,6 +37,11 @@ impl Wallet {
         Ok(Self {
             balance,
             fees: 0.0,
+            slippage: 0.0,
+            funding: 0.0,
+            borrow: 0.0,
+            short_collateral: 0.0,
+            shortfall: 0.0,
             locked: 0.0,
             unrealized_pnl: 0.0,
             initial_balance: balance,
@@ -54,8 +69,15 @@ impl Wallet {
     }
 
     /// Returns the total balance.
+    ///
+    /// As the cost of the long positions, the collateral of the short positions is excluded.
     pub fn total_balance(&self) -> f64 {
-        self.balance + self.unrealized_pnl
+        self.balance - self.short_collateral + self.unrealized_pnl
+    }
+
+    /// Returns the collateral held by the open short positions (proceeds of the sales and initial margin).
+    pub fn short_collateral(&self) -> f64 {
+        self.short_collateral
     }
 
     /// Returns the free balance (available for new trades).
@@ -72,6 +94,26 @@ impl Wallet {
         self.fees
     }
 
+    /// Returns the cost of the slippage on the fills, already included in the fill prices.
+    pub fn slippage_paid(&self) -> f64 {
+        self.slippage
+    }
+
+    /// Returns the funding paid by the perpetual positions, negative when received.
+    pub fn funding_paid(&self) -> f64 {
+        self.funding
+    }
+
+    /// Returns the borrow fees paid by the short positions.
+    pub fn borrow_paid(&self) -> f64 {
+        self.borrow
+    }
+
+    /// Returns the losses of the liquidations not covered by the balance (e.g., after a gap).
+    pub fn shortfall(&self) -> f64 {
+        self.shortfall
+    }
+
     /// Adds funds to the wallet.
     pub(crate) fn add(&mut self, amount: f64) -> Result<f64> {
         self.balance += amount;
@@ -93,6 +135,51 @@ impl Wallet {
         self.free_balance()
     }
 
+    /// Credits the proceeds of a short sale, held as collateral with the initial margin already locked.
+    pub(crate) fn credit_short(&mut self, proceeds: f64, margin: f64) {
+        self.balance += proceeds;
+        self.locked += proceeds;
+        self.short_collateral += proceeds + margin;
+    }
+
+    /// Releases the collateral of a short position (proceeds and initial margin) once bought back.
+    pub(crate) fn release_short(&mut self, proceeds: f64, margin: f64) -> Result<()> {
+        self.unlock(proceeds + margin)?;
+        self.short_collateral -= proceeds + margin;
+        Ok(())
+    }
+
+    /// Adds the profit/loss of a liquidated position, writing off the loss not covered by the free balance.
+    ///
+    /// ### Returns
+    /// The shortfall of the liquidation.
+    pub(crate) fn add_liquidation(&mut self, amount: f64) -> f64 {
+        self.balance += amount;
+        let shortfall = (self.locked - self.balance).max(0.0);
+        self.balance += shortfall;
+        self.shortfall += shortfall;
+        shortfall
+    }
+
+    /// Subtracts a funding payment from the balance, or adds it when negative.
+    pub(crate) fn sub_funding(&mut self, amount: f64) -> Result<f64> {
+        self.balance -= amount;
+        self.funding += amount;
+        self.free_balance()
+    }
+
+    /// Subtracts the borrow fee of the short positions from the balance.
+    pub(crate) fn sub_borrow(&mut self, amount: f64) -> Result<f64> {
+        self.balance -= amount;
+        self.borrow += amount;
+        self.free_balance()
+    }
+
+    /// Records the cost of the slippage on a fill.
+    pub(crate) fn add_slippage(&mut self, amount: f64) {
+        self.slippage += amount;
+    }
+
     /// Locks additional funds for a position.
     pub(crate) fn lock(&mut self, amount: f64) -> Result<()> {
         if amount <= 0.0 {
@@ -134,6 +221,11 @@ impl Wallet {
     /// Resets the wallet to its initial balance.
     pub(crate) fn reset(&mut self) {
         self.fees = 0.0;
+        self.slippage = 0.0;
+        self.funding = 0.0;
+        self.borrow = 0.0;
+        self.short_collateral = 0.0;
+        self.shortfall = 0.0;
         self.locked = 0.0;
         self.unrealized_pnl = 0.0;
         self.balance = self.initial_balance;
@@ -303,3 +395,35 @@ fn unrealized_pnl() {
     assert_eq!(wallet.total_balance(), 95.0);
     assert_eq!(wallet.free_balance().unwrap(), 100.0);
 }
+
+#[cfg(test)]
+#[test]
+fn open_close_short_position() {
+    let mut wallet = Wallet::new(100.0).unwrap();
+    wallet.lock(20.0).unwrap();
+    wallet.credit_short(20.0, 20.0);
+    assert_eq!(wallet.balance, 120.0);
+    assert_eq!(wallet.short_collateral(), 40.0);
+    assert_eq!(wallet.total_balance(), 80.0);
+    assert_eq!(wallet.free_balance().unwrap(), 80.0);
+
+    // bought back at 15
+    wallet.release_short(20.0, 20.0).unwrap();
+    wallet.add(5.0 - 20.0).unwrap();
+    assert_eq!(wallet.balance, 105.0);
+    assert_eq!(wallet.total_balance(), 105.0);
+    assert_eq!(wallet.free_balance().unwrap(), 105.0);
+}
+
+#[cfg(test)]
+#[test]
+fn liquidation_shortfall() {
+    let mut wallet = Wallet::new(100.0).unwrap();
+    wallet.lock(20.0).unwrap();
+    assert_eq!(wallet.add_liquidation(-90.0), 10.0);
+    assert_eq!(wallet.balance, 20.0);
+    assert_eq!(wallet.free_balance().unwrap(), 0.0);
+    assert_eq!(wallet.shortfall(), 10.0);
+    assert_eq!(wallet.add_liquidation(5.0), 0.0);
+    assert_eq!(wallet.shortfall(), 10.0);
+}
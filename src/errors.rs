@@ -62,8 +62,10 @@ pub enum Error {
     #[error("Negative free balance: balance={0}, locked={1}")]
     NegFreeBalance(f64, f64),
 
-    /// The fees are negative.
-    #[error("Negative fees")]
+    /// The taker fee is negative, or a fee is not finite.
+    ///
+    /// Only the maker fee can be negative, as a rebate credited to the wallet.
+    #[error("Negative taker fee or non-finite fees")]
     NegZeroFees,
 
     /// The locked funds are insufficient for the requested amount.
@@ -94,6 +96,17 @@ pub enum Error {
     #[error("Invalid exit price {0}")]
     ExitPrice(f64),
 
+    /// The quantity is invalid (not positive, or greater than the position quantity).
+    #[error("Invalid quantity {0}")]
+    InvalidQuantity(f64),
+
+    /// The position has already been added to the maximum number of times.
+    ///
+    /// ### Arguments
+    /// * `0` - The maximum pyramid count.
+    #[error("Maximum pyramid count {0} reached")]
+    MaxPyramid(usize),
+
     /// A generic error with a custom message.
     ///
     /// ### Arguments
@@ -105,6 +118,28 @@ pub enum Error {
     #[error("TakeProfit or StopLoss must be positive")]
     NegTakeProfitAndStopLoss,
 
+    /// A fraction of a position must be greater than 0 and lower than or equal to 1.
+    #[error("Fraction must be in ]0, 1] (got: {0})")]
+    InvalidFraction(f64),
+
+    /// The leverage is lower than 1, or a margin fraction is out of range.
+    ///
+    /// ### Arguments
+    /// * `0` - The invalid value.
+    #[error("Invalid leverage or margin fraction (got: {0})")]
+    InvalidMargin(f64),
+
+    /// A rate is negative or not finite.
+    ///
+    /// ### Arguments
+    /// * `0` - The invalid rate.
+    #[error("Rate must be finite and non-negative (got: {0})")]
+    InvalidRate(f64),
+
+    /// The asset is hard to borrow, so short orders are rejected.
+    #[error("Hard to borrow: short orders are rejected")]
+    HardToBorrow,
+
     /// Trailing stop values must be positive.
     #[error("TrailingStop must be positive and greater than 0")]
     NegZeroTrailingStop,
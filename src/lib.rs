@@ -30,10 +30,18 @@
 //! |--------------------------|-------------------------------------------------------------------------------------------------|
 //! | **Market Order**         | Executes immediately at the current price.                                                    |
 //! | **Limit Order**          | Executes only at a specified price or better.                                                 |
+//! | **Stop Order**           | Executes as a market order once the price trades through a trigger (breakout entries).       |
+//! | **Stop-Limit Order**     | Rests as a limit order once the price trades through a trigger, and may stay unfilled.       |
 //! | **Take-Profit**          | Closes the position when a target price is reached.                                          |
 //! | **Stop-Loss**            | Closes the position to limit losses.                                                          |
-//! | **Trailing Stop**        | Dynamically adjusts the stop price based on market movements.                                |
+//! | **Trailing Stop**        | Dynamically adjusts the stop price by a percentage or an absolute distance, optionally from an activation price. |
 //! | **Take-Profit + Stop-Loss** | Combines both rules for risk management.                                                   |
+//! | **Take-Profit Ladder**   | Closes fractions of the position at several targets, with a stop-loss on the remainder.      |
+//! | **Break-Even Stop**      | Moves the stop-loss to the entry price plus fees once a trigger price is reached.             |
+//! | **Stepped Stop**         | Moves the stop-loss by a fixed step each time the price advances by that step.              |
+//! | **Time Stop**            | Closes the position at the candle close after N candles or at a given time.                |
+//! | **Reduce-Only / Close**  | Exit orders targeting an open position, filled like entries inside the candle range.         |
+//! | **OCO / Bracket**        | Linked orders: one-cancels-other, or an entry with take-profit and stop-loss child orders.   |
 //!
 //! ### 3. **Performance Metrics**
 //! | Metric               | Description                                                                                     |
@@ -34,6 +34,29 @@ pub enum Event {
     /// This event is triggered when an order is canceled or executed.
     DelOrder(Order),
 
+    /// An order has expired.
+    ///
+    /// This event is triggered when an order is not filled within its time-in-force,
+    /// right before it is removed.
+    ExpireOrder(Order),
+
+    /// A stop order has been triggered.
+    ///
+    /// This event is triggered when the candle trades through the stop price of an order.
+    /// A stop order is then filled, while a stop-limit order rests as a limit order.
+    TriggerOrder(Order),
+
+    /// An order has been partially filled.
+    ///
+    /// This event is triggered when an order is larger than the quantity the candle volume allows.
+    /// It contains the filled quantity only, while the remaining quantity stays in the pending orders.
+    PartialFillOrder(Order),
+
+    /// An order has been amended.
+    ///
+    /// This event is triggered when the price, quantity or exit rule of a pending order is changed.
+    AmendOrder(Order),
+
     /// A position has been opened.
     ///
     /// This event is triggered when an order is executed and a new position is created.
@@ -42,8 +65,20 @@ pub enum Event {
     /// A position has been closed.
     ///
     /// This event is triggered when a position is closed, either manually or by an exit rule.
+    /// On a partial close, it contains the closed quantity only, while the rest stays open.
     DelPosition(Position),
 
+    /// A position has been amended.
+    ///
+    /// This event is triggered when the exit rule of an open position is changed.
+    AmendPosition(Position),
+
+    /// A position has been liquidated.
+    ///
+    /// This event is triggered when the equity falls below the maintenance margin,
+    /// right before the position is closed at the candle close.
+    Liquidation(Position),
+
     /// The wallet balance has been updated.
     ///
     /// This event is triggered after each trade or fee deduction.
@@ -53,12 +88,22 @@ pub enum Event {
         pnl: f64,
         /// Total fees paid.
         fees: f64,
+        /// Total slippage cost, already included in the fill prices.
+        slippage: f64,
+        /// Total funding paid, negative when received.
+        funding: f64,
+        /// Total borrow fees paid by the short positions.
+        borrow: f64,
         /// Available funds (not locked in open positions).
         free: f64,
         /// Funds locked in open positions.
         locked: f64,
         /// Total balance (free + locked + unrealized P&L).
         balance: f64,
+        /// Balance without the collateral of the short positions, as the cost of the long positions.
+        ///
+        /// The drawdown and the Sharpe ratio are computed from it, the proceeds of a short being owed back.
+        equity: f64,
     },
 }
 
@@ -67,7 +112,11 @@ impl From<&Wallet> for Event {
         Self::WalletUpdate {
             locked: value.locked(),
             fees: value.fees_paid(),
+            slippage: value.slippage_paid(),
+            funding: value.funding_paid(),
+            borrow: value.borrow_paid(),
             balance: value.balance(),
+            equity: value.balance() - value.short_collateral(),
             pnl: value.unrealized_pnl(),
             free: value.free_balance().expect("should give the free balance"),
         }
@@ -108,8 +157,8 @@ impl Metrics {
         let mut balance_history = Vec::new();
 
         for event in &self.events {
-            if let Event::WalletUpdate { balance, .. } = event {
-                balance_history.push(*balance);
+            if let Event::WalletUpdate { equity, .. } = event {
+                balance_history.push(*equity);
             }
         }
 
@@ -161,10 +210,10 @@ impl Metrics {
         let mut previous_balance = self.initial_balance;
 
         for event in &self.events {
-            if let Event::WalletUpdate { balance, .. } = event {
-                let return_pct = (*balance - previous_balance) / previous_balance;
+            if let Event::WalletUpdate { equity, .. } = event {
+                let return_pct = (*equity - previous_balance) / previous_balance;
                 returns.push(return_pct);
-                previous_balance = *balance;
+                previous_balance = *equity;
             }
         }
 
@@ -174,6 +223,30 @@ impl Metrics {
         (mean_return - risk_free_rate) / std_dev
     }
 
+    /// Returns the cumulative funding paid, negative when received.
+    pub fn funding_paid(&self) -> f64 {
+        self.events
+            .iter()
+            .rev()
+            .find_map(|event| match event {
+                Event::WalletUpdate { funding, .. } => Some(*funding),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the cumulative borrow fees paid by the short positions.
+    pub fn borrow_paid(&self) -> f64 {
+        self.events
+            .iter()
+            .rev()
+            .find_map(|event| match event {
+                Event::WalletUpdate { borrow, .. } => Some(*borrow),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
     /// Computes the win rate as a percentage of winning trades.
     pub fn win_rate(&self) -> f64 {
         let mut winning_trades = 0;
@@ -204,6 +277,8 @@ impl fmt::Display for Metrics {
         writeln!(f, "Profit Factor: {:.2}", self.profit_factor())?;
         writeln!(f, "Sharpe Ratio (risk-free rate = 0.0): {:.2}", self.sharpe_ratio(0.0))?;
         writeln!(f, "Win Rate: {:.2}%", self.win_rate())?;
+        writeln!(f, "Funding Paid: {:.2}", self.funding_paid())?;
+        writeln!(f, "Borrow Paid: {:.2}", self.borrow_paid())?;
         Ok(())
     }
 }
@@ -227,30 +302,46 @@ fn max_drawdown() {
         Event::WalletUpdate {
             pnl: 0.0,
             fees: 0.0,
+            slippage: 0.0,
+            funding: 0.0,
+            borrow: 0.0,
             free: 10000.0,
             locked: 0.0,
             balance: 10000.0,
+            equity: 10000.0,
         },
         Event::WalletUpdate {
             pnl: 0.0,
             fees: 0.0,
+            slippage: 0.0,
+            funding: 0.0,
+            borrow: 0.0,
             free: 12000.0,
             locked: 0.0,
             balance: 12000.0,
+            equity: 12000.0,
         },
         Event::WalletUpdate {
             pnl: 0.0,
             fees: 0.0,
+            slippage: 0.0,
+            funding: 0.0,
+            borrow: 0.0,
             free: 9000.0,
             locked: 0.0,
             balance: 9000.0,
+            equity: 9000.0,
         },
         Event::WalletUpdate {
             pnl: 0.0,
             fees: 0.0,
+            slippage: 0.0,
+            funding: 0.0,
+            borrow: 0.0,
             free: 11000.0,
             locked: 0.0,
             balance: 11000.0,
+            equity: 11000.0,
         },
     ];
     let metrics = Metrics::new(events, 10000.0);
@@ -300,30 +391,46 @@ fn sharpe_ratio() {
         Event::WalletUpdate {
             pnl: 0.0,
             fees: 0.0,
+            slippage: 0.0,
+            funding: 0.0,
+            borrow: 0.0,
             free: 10000.0,
             locked: 0.0,
             balance: 10000.0,
+            equity: 10000.0,
         },
         Event::WalletUpdate {
             pnl: 0.0,
             fees: 0.0,
+            slippage: 0.0,
+            funding: 0.0,
+            borrow: 0.0,
             free: 10500.0,
             locked: 0.0,
             balance: 10500.0,
+            equity: 10500.0,
         },
         Event::WalletUpdate {
             pnl: 0.0,
             fees: 0.0,
+            slippage: 0.0,
+            funding: 0.0,
+            borrow: 0.0,
             free: 10300.0,
             locked: 0.0,
             balance: 10300.0,
+            equity: 10300.0,
         },
         Event::WalletUpdate {
             pnl: 0.0,
             fees: 0.0,
+            slippage: 0.0,
+            funding: 0.0,
+            borrow: 0.0,
             free: 10700.0,
             locked: 0.0,
             balance: 10700.0,
+            equity: 10700.0,
         },
     ];
     let metrics = Metrics::new(events, 10000.0);
@@ -368,3 +475,49 @@ fn win_rate_all_winning() {
     let metrics = Metrics::new(events, 10000.0);
     assert_eq!(metrics.win_rate(), 100.0); // 1 win out of 1 trade
 }
+
+#[cfg(test)]
+fn run_trade(side: OrderSide, exit_price: f64) -> Metrics {
+    use chrono::DateTime;
+
+    let data = (0..2)
+        .map(|i| {
+            CandleBuilder::builder()
+                .open(100.0)
+                .high(110.0)
+                .low(90.0)
+                .close(100.0)
+                .volume(1.0)
+                .open_time(DateTime::from_timestamp_secs(1515151515 + i).unwrap())
+                .close_time(DateTime::from_timestamp_secs(1515151516 + i).unwrap())
+                .build()
+                .unwrap()
+        })
+        .collect();
+    let mut bt = Backtest::new(data, 1000.0, None).unwrap();
+    bt.run(|bt, _| {
+        match bt.positions().next().cloned() {
+            None => {
+                bt.place_order(Order::from((OrderType::Market(100.0), 5.0, side.clone())))?;
+            }
+            Some(position) => {
+                bt.close_position(&position, exit_price, true)?;
+            }
+        }
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(bt.balance(), 1050.0);
+    Metrics::from(&bt)
+}
+
+#[cfg(test)]
+#[test]
+fn short_trade_metrics() {
+    let long = run_trade(OrderSide::Buy, 110.0);
+    let short = run_trade(OrderSide::Sell, 90.0);
+    // the proceeds of the short are not counted as a gain, the equity follows the long one
+    assert_eq!(short.max_drawdown(), long.max_drawdown());
+    assert_eq!(short.max_drawdown(), 50.0);
+    assert_eq!(short.sharpe_ratio(0.0), long.sharpe_ratio(0.0));
+}
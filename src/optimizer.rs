@@ -45,7 +45,7 @@ impl<PS: ParameterCombination> Optimizer<PS> {
     /// # Arguments
     /// * `data` - Historical candle data for backtesting.
     /// * `initial_balance` - Starting balance for the backtest.
-    /// * `market_fees` - Optional tuple of (maker fee, taker fee).
+    /// * `market_fees` - Optional tuple of (taker fee, maker fee).
     ///
     /// # Returns
     /// A new `Optimizer` instance.
@@ -80,7 +80,7 @@ impl<PS: ParameterCombination> Optimizer<PS> {
         let num_cpus = num_cpus::get();
         let strategy = Arc::new(Mutex::new(strategy));
         let combinations = PS::generate();
-        let chunk_size = ((combinations.len() + num_cpus - 1) / num_cpus).max(1);
+        let chunk_size = combinations.len().div_ceil(num_cpus).max(1);
 
         let chunk_results = combinations
             .par_chunks(chunk_size)
@@ -1,6 +1,30 @@
 //! Utility functions and helpers.
 
-/// Generates a random ID.
-pub(crate) fn random_id() -> u32 {
-    rand::random()
+/// Generates monotonic ids, so two runs on the same data give the same ids.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default)]
+pub(crate) struct IdGenerator(u32);
+
+impl IdGenerator {
+    /// Returns the next id.
+    pub(crate) fn next_id(&mut self) -> u32 {
+        self.0 += 1;
+        self.0
+    }
+
+    /// Restarts the ids from the beginning.
+    pub(crate) fn reset(&mut self) {
+        self.0 = 0;
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn monotonic_ids() {
+    let mut ids = IdGenerator::default();
+    assert_eq!(ids.next_id(), 1);
+    assert_eq!(ids.next_id(), 2);
+
+    ids.reset();
+    assert_eq!(ids.next_id(), 1);
 }